@@ -1,41 +1,326 @@
-use crate::translation::{CombinedTranslationData, ExampleItem, TranslationItem};
+use crate::cache::Lookup;
+use crate::config::{Theme, UiConfig};
+use crate::history::{self, HistoryEntry, HistoryFilter};
+use crate::languages;
+use crate::ocr::OcrResult;
+use crate::output;
+use crate::translation::{
+    CombinedTranslationData, ExampleItem, TranslateResult, TranslationError, TranslationItem,
+};
+use crate::vocab::Vocabulary;
+use crate::window_state::WindowState;
 use eframe::egui;
 use std::fmt;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
 
-const MAX_HEIGHT: f32 = 720.0;
-const MIN_HEIGHT: f32 = 160.0;
+/// How long the "Copied ..." notice stays visible.
+const COPY_NOTICE_DURATION: Duration = Duration::from_secs(2);
+
+/// A pinned window that was moved has its position saved once it stays put
+/// this long, rather than on every step of the drag.
+const POSITION_SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// A finished lookup, kept for back/forward navigation.
+struct PastLookup {
+    text: String,
+    data: CombinedTranslationData,
+}
+
+/// Something the user asked for while the UI was being drawn.
+enum UiAction {
+    Retry,
+    Submit,
+    Back,
+    Forward,
+    OpenHistory(usize),
+    /// Put `text` on the clipboard; `what` names it in the confirmation.
+    Copy {
+        text: String,
+        what: &'static str,
+    },
+}
 
 // App struct for the egui UI
 pub struct OcrApp {
     pub text: String,
+    /// Contents of the editable search field; looked up when Enter is pressed.
+    pub query: String,
+    pub ocr_result: Option<OcrResult>,
     pub translation_data: Option<CombinedTranslationData>,
+    pub translation_error: Option<TranslationError>,
     pub is_translating: bool,
-    pub translation_rx: Receiver<CombinedTranslationData>,
-    pub translation_started: bool,
+    pub translation_rx: Receiver<TranslateResult<CombinedTranslationData>>,
+    /// A pinned window stays on top and does not close on focus loss.
+    pub pinned: bool,
+    lookup: Lookup,
+    back: Vec<PastLookup>,
+    forward: Vec<PastLookup>,
+    show_history: bool,
+    history_entries: Vec<HistoryEntry>,
+    history_filter: HistoryFilter,
+    /// Saved words; `None` if the vocabulary file could not be read.
+    vocabulary: Option<Vocabulary>,
+    /// Kept open while the window is: on X11 copied text is served by its owner.
+    clipboard: Option<arboard::Clipboard>,
+    copy_notice: Option<(&'static str, Instant)>,
+    /// The window grows with its content between these heights (see `UiConfig`).
+    min_height: f32,
+    max_height: f32,
+    /// The pinned position last written to `WindowState`.
+    saved_position: Option<egui::Pos2>,
+    /// Where a pinned window was moved to and when, until it is saved.
+    moved_to: Option<(egui::Pos2, Instant)>,
     frame_count: u32,
 }
 
 impl OcrApp {
-    pub fn new(text: String, translation_rx: Receiver<CombinedTranslationData>) -> Self {
+    pub fn new(
+        text: String,
+        ocr_result: Option<OcrResult>,
+        lookup: Lookup,
+        translation_rx: Receiver<TranslateResult<CombinedTranslationData>>,
+        pinned: bool,
+    ) -> Self {
         Self {
+            query: text.clone(),
             text,
+            ocr_result,
             translation_data: None,
+            translation_error: None,
             is_translating: true,
             translation_rx,
-            translation_started: true,
+            pinned,
+            lookup,
+            back: Vec::new(),
+            forward: Vec::new(),
+            show_history: false,
+            history_entries: Vec::new(),
+            history_filter: HistoryFilter::default(),
+            vocabulary: Vocabulary::open().ok(),
+            clipboard: None,
+            copy_notice: None,
+            min_height: UiConfig::default().min_height,
+            max_height: UiConfig::default().max_height,
+            saved_position: None,
+            moved_to: None,
             frame_count: 0,
         }
     }
+
+    /// Opens the window on the history list, without a current lookup.
+    pub fn with_history(lookup: Lookup, pinned: bool) -> Self {
+        let mut app = Self::new(String::new(), None, lookup, channel().1, pinned);
+        app.is_translating = false;
+        app.show_history = true;
+        app.history_entries = app.load_history();
+        app
+    }
+
+    fn set_pinned(&mut self, ctx: &egui::Context, pinned: bool) {
+        if self.pinned && !pinned {
+            self.save_pinned_position(ctx);
+        }
+        self.pinned = pinned;
+        let level = if pinned {
+            egui::WindowLevel::AlwaysOnTop
+        } else {
+            egui::WindowLevel::Normal
+        };
+        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
+    }
+
+    /// Remembers where the pinned window is, so the next pinned window opens there.
+    /// Called when it is closed or unpinned.
+    fn save_pinned_position(&mut self, ctx: &egui::Context) {
+        if let Some(rect) = ctx.input(|i| i.viewport().outer_rect) {
+            self.write_pinned_position(rect.min);
+        }
+    }
+
+    /// Called every frame while pinned; saves the position after a move (see
+    /// `POSITION_SAVE_DELAY`).
+    fn track_pinned_position(&mut self, ctx: &egui::Context) {
+        let Some(position) = ctx.input(|i| i.viewport().outer_rect).map(|rect| rect.min) else {
+            return;
+        };
+        if self.saved_position == Some(position) {
+            self.moved_to = None;
+            return;
+        }
+        match self.moved_to {
+            Some((moved_to, at)) if moved_to == position => {
+                let waited = at.elapsed();
+                if waited >= POSITION_SAVE_DELAY {
+                    self.write_pinned_position(position);
+                } else {
+                    ctx.request_repaint_after(POSITION_SAVE_DELAY - waited);
+                }
+            }
+            _ => {
+                self.moved_to = Some((position, Instant::now()));
+                ctx.request_repaint_after(POSITION_SAVE_DELAY);
+            }
+        }
+    }
+
+    fn write_pinned_position(&mut self, position: egui::Pos2) {
+        self.moved_to = None;
+        if self.saved_position == Some(position) {
+            return;
+        }
+        let state = WindowState {
+            pinned_position: Some([position.x, position.y]),
+        };
+        // Losing the position is harmless, so a failed write is ignored.
+        let _ = state.save();
+        self.saved_position = Some(position);
+    }
+
+    /// Looks up the current text in the background, e.g. again after a network error.
+    fn start_lookup(&mut self, ctx: &egui::Context) {
+        self.translation_error = None;
+        self.is_translating = true;
+        self.translation_rx = self.lookup.spawn(&self.text);
+        allow_auto_resize(ctx);
+    }
+
+    /// Looks up the edited search term, keeping the current result for the Back button.
+    fn submit_query(&mut self, ctx: &egui::Context) {
+        let query = self.query.trim().to_string();
+        if query.is_empty() || (query == self.text && self.translation_error.is_none()) {
+            return;
+        }
+
+        if let Some(data) = self.translation_data.take() {
+            self.back.push(PastLookup {
+                text: std::mem::take(&mut self.text),
+                data,
+            });
+        }
+        self.forward.clear();
+        self.text = query.clone();
+        self.query = query;
+        self.start_lookup(ctx);
+    }
+
+    fn go_back(&mut self, ctx: &egui::Context) {
+        if let Some(previous) = self.back.pop() {
+            if let Some(current) = self.take_current() {
+                self.forward.push(current);
+            }
+            self.show_past(previous, ctx);
+        }
+    }
+
+    fn go_forward(&mut self, ctx: &egui::Context) {
+        if let Some(next) = self.forward.pop() {
+            if let Some(current) = self.take_current() {
+                self.back.push(current);
+            }
+            self.show_past(next, ctx);
+        }
+    }
+
+    /// Adds the current word and its definitions to the vocabulary list for review.
+    fn save_word(&mut self) {
+        let (Some(vocabulary), Some(data)) = (&mut self.vocabulary, &self.translation_data) else {
+            return;
+        };
+        vocabulary.add(&self.text, data);
+        if let Err(e) = vocabulary.save() {
+            eprintln!("Failed to save the vocabulary: {}", e);
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, text: &str, what: &'static str) {
+        if self.clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(e) => {
+                    eprintln!("Failed to open the clipboard: {}", e);
+                    return;
+                }
+            }
+        }
+        let Some(clipboard) = &mut self.clipboard else {
+            return;
+        };
+        match clipboard.set_text(text.trim()) {
+            Ok(()) => self.copy_notice = Some((what, Instant::now())),
+            Err(e) => eprintln!("Failed to copy to the clipboard: {}", e),
+        }
+    }
+
+    fn set_show_history(&mut self, ctx: &egui::Context, show: bool) {
+        self.show_history = show;
+        if show {
+            self.history_entries = self.load_history();
+        }
+        allow_auto_resize(ctx);
+    }
+
+    fn load_history(&self) -> Vec<HistoryEntry> {
+        self.lookup
+            .history
+            .as_ref()
+            .and_then(|history| history.load().ok())
+            .unwrap_or_default()
+    }
+
+    /// Shows a past result from the history without looking it up again.
+    fn open_history_entry(&mut self, ctx: &egui::Context, index: usize) {
+        let Some(entry) = self.history_entries.get(index).cloned() else {
+            return;
+        };
+        // Drop any lookup still in flight, so its result does not replace this one.
+        self.is_translating = false;
+        self.translation_rx = channel().1;
+
+        if let Some(current) = self.take_current() {
+            self.back.push(current);
+        }
+        self.forward.clear();
+        self.show_history = false;
+        self.show_past(
+            PastLookup {
+                text: entry.text,
+                data: entry.data,
+            },
+            ctx,
+        );
+    }
+
+    fn take_current(&mut self) -> Option<PastLookup> {
+        let data = self.translation_data.take()?;
+        Some(PastLookup {
+            text: std::mem::take(&mut self.text),
+            data,
+        })
+    }
+
+    fn show_past(&mut self, past: PastLookup, ctx: &egui::Context) {
+        self.query = past.text.clone();
+        self.text = past.text;
+        self.translation_data = Some(past.data);
+        self.translation_error = None;
+        allow_auto_resize(ctx);
+    }
 }
 
 impl fmt::Debug for OcrApp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("OcrApp")
             .field("text", &self.text)
+            .field("query", &self.query)
+            .field("ocr_result", &self.ocr_result)
             .field("translation_data", &self.translation_data)
+            .field("translation_error", &self.translation_error)
             .field("is_translating", &self.is_translating)
-            .field("translation_started", &self.translation_started)
+            .field("pinned", &self.pinned)
+            .field("back", &self.back.len())
+            .field("forward", &self.forward.len())
+            .field("show_history", &self.show_history)
             .field("frame_count", &self.frame_count)
             .finish()
     }
@@ -46,15 +331,134 @@ impl eframe::App for OcrApp {
         self.frame_count += 1;
 
         // Check if translation is complete
-        if let Ok(data) = self.translation_rx.try_recv() {
-            self.translation_data = Some(data);
-            self.is_translating = false;
+        match self.translation_rx.try_recv() {
+            Ok(Ok(data)) => {
+                self.translation_data = Some(data);
+                self.is_translating = false;
+            }
+            Ok(Err(e)) => {
+                self.translation_error = Some(e);
+                self.is_translating = false;
+            }
+            Err(TryRecvError::Disconnected) if self.is_translating => {
+                self.translation_error = Some(TranslationError::Network(
+                    "The translation stopped unexpectedly".to_string(),
+                ));
+                self.is_translating = false;
+            }
+            Err(_) => {}
+        }
+
+        // History and pin toggles (buttons, Ctrl+H and Ctrl+P)
+        let mut pinned = self.pinned;
+        let mut show_history = self.show_history;
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::P)) {
+            pinned = !pinned;
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::H)) {
+            show_history = !show_history;
+        }
+        let can_save = self.translation_data.is_some() && !self.show_history;
+        let is_saved = self
+            .vocabulary
+            .as_ref()
+            .is_some_and(|vocabulary| vocabulary.contains(&self.text));
+        let mut save_clicked = false;
+        let mut copy_all_clicked = false;
+        let copy_notice = self
+            .copy_notice
+            .filter(|(_, at)| at.elapsed() < COPY_NOTICE_DURATION)
+            .map(|(what, _)| what);
+        egui::Area::new(egui::Id::new("window_toggles"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if let Some(what) = copy_notice {
+                        ui.label(
+                            egui::RichText::new(format!("Copied {}", what))
+                                .size(12.0)
+                                .color(ui.visuals().weak_text_color()),
+                        );
+                    }
+                    if can_save {
+                        copy_all_clicked = ui
+                            .button("Copy all")
+                            .on_hover_text("Copy the whole result as Markdown (Ctrl+Shift+M)")
+                            .clicked();
+                        let label = if is_saved { "Saved" } else { "Save" };
+                        save_clicked = ui
+                            .add_enabled(!is_saved, egui::Button::new(label))
+                            .on_hover_text("Add this word to the vocabulary list (Ctrl+S)")
+                            .clicked();
+                    }
+                    ui.toggle_value(&mut show_history, "History")
+                        .on_hover_text("Browse past lookups (Ctrl+H)");
+                    ui.toggle_value(&mut pinned, "Pin")
+                        .on_hover_text("Keep this window open and on top (Ctrl+P)");
+                });
+            });
+        if can_save
+            && !is_saved
+            && ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::S))
+        {
+            save_clicked = true;
+        }
+        if save_clicked {
+            self.save_word();
+        }
+        if copy_notice.is_some() {
+            ctx.request_repaint_after(COPY_NOTICE_DURATION);
+        }
+        if pinned != self.pinned {
+            self.set_pinned(ctx, pinned);
+        }
+        if show_history != self.show_history {
+            self.set_show_history(ctx, show_history);
+        }
+
+        if self.pinned {
+            if ctx.input(|i| i.viewport().close_requested()) {
+                self.save_pinned_position(ctx);
+            } else {
+                self.track_pinned_position(ctx);
+            }
         }
 
-        setup_visuals(ctx);
+        // Back/forward through earlier lookups (Alt+Left / Alt+Right)
+        let mut action = None;
+        if !self.is_translating {
+            if ctx.input_mut(|i| i.consume_key(egui::Modifiers::ALT, egui::Key::ArrowLeft)) {
+                action = Some(UiAction::Back);
+            } else if ctx.input_mut(|i| i.consume_key(egui::Modifiers::ALT, egui::Key::ArrowRight))
+            {
+                action = Some(UiAction::Forward);
+            }
+        }
 
-        // Close on focus loss
-        if self.frame_count > 2 {
+        // Copy shortcuts: Ctrl+Shift+C (translation), Ctrl+Shift+O (search term), Ctrl+Shift+M (all)
+        if let (Some(data), false) = (&self.translation_data, self.show_history) {
+            let ctrl_shift = egui::Modifiers::CTRL | egui::Modifiers::SHIFT;
+            if ctx.input_mut(|i| i.consume_key(ctrl_shift, egui::Key::C)) {
+                action = Some(UiAction::Copy {
+                    text: data.summary().to_string(),
+                    what: "translation",
+                });
+            } else if ctx.input_mut(|i| i.consume_key(ctrl_shift, egui::Key::O)) {
+                action = Some(UiAction::Copy {
+                    text: self.text.clone(),
+                    what: "text",
+                });
+            } else if copy_all_clicked || ctx.input_mut(|i| i.consume_key(ctrl_shift, egui::Key::M))
+            {
+                action = Some(UiAction::Copy {
+                    text: output::markdown(data),
+                    what: "as Markdown",
+                });
+            }
+        }
+
+        // Close on focus loss, unless pinned
+        if self.frame_count > 2 && !self.pinned {
             let is_focused = ctx.input(|i| i.focused);
             if !is_focused {
                 ctx.send_viewport_cmd(egui::ViewportCommand::Close);
@@ -62,15 +466,28 @@ impl eframe::App for OcrApp {
         }
 
         // Central Panel - measure content height
+        let max_height = self.max_height;
+        let can_go_back = !self.back.is_empty();
+        let can_go_forward = !self.forward.is_empty();
         let inner_response = egui::CentralPanel::default()
             .frame(egui::Frame {
-                fill: egui::Color32::from_rgb(28, 28, 32),
+                fill: ctx.style().visuals.panel_fill,
                 inner_margin: egui::Margin::same(16.0),
                 stroke: egui::Stroke::new(0.0, egui::Color32::TRANSPARENT),
                 ..Default::default()
             })
             .show(ctx, |ui| {
-                if self.is_translating {
+                if self.show_history {
+                    // History View
+                    let (height, history_action) = render_history(
+                        ui,
+                        &self.history_entries,
+                        &mut self.history_filter,
+                        max_height,
+                    );
+                    action = action.take().or(history_action);
+                    Some(height)
+                } else if self.is_translating {
                     // Loading View
                     ui.vertical_centered(|ui| {
                         ui.add_space(40.0);
@@ -79,7 +496,7 @@ impl eframe::App for OcrApp {
                         ui.label(
                             egui::RichText::new("Translating...")
                                 .size(16.0)
-                                .color(egui::Color32::from_gray(200)),
+                                .color(ui.visuals().text_color()),
                         );
                         ui.add_space(40.0);
                     });
@@ -87,25 +504,62 @@ impl eframe::App for OcrApp {
                 } else if let Some(data) = &self.translation_data {
                     // Results View with ScrollArea
                     let scroll_response = egui::ScrollArea::vertical()
-                        .max_height(MAX_HEIGHT - 32.0) // 800 - 32 margins
+                        .max_height(max_height - 32.0) // minus the margins
                         .show(ui, |ui| {
                             // Set a max width to ensure proper wrapping
                             ui.set_max_width(ui.available_width()); // window width - margins
 
-                            render_content(ui, &self.text, data);
+                            let term_action = render_term(
+                                ui,
+                                &mut self.query,
+                                self.ocr_result.as_ref(),
+                                can_go_back,
+                                can_go_forward,
+                            );
+                            action = action.take().or(term_action);
+                            let content_action = render_content(ui, data);
+                            action = action.take().or(content_action);
 
                             // Return the content height for resize calculation
                             ui.min_rect().height()
                         });
 
                     Some(scroll_response.inner)
+                } else if let Some(error) = &self.translation_error {
+                    // Error View
+                    let term_action = render_term(
+                        ui,
+                        &mut self.query,
+                        self.ocr_result.as_ref(),
+                        can_go_back,
+                        can_go_forward,
+                    );
+                    action = action.take().or(term_action);
+                    if render_error(ui, error) {
+                        action = Some(UiAction::Retry);
+                    }
+                    None
                 } else {
+                    // Empty View (e.g. opened on the history): type a word to look it up
+                    let term_action =
+                        render_term(ui, &mut self.query, None, can_go_back, can_go_forward);
+                    action = action.take().or(term_action);
                     None
                 }
             });
 
+        match action {
+            Some(UiAction::Retry) => self.start_lookup(ctx),
+            Some(UiAction::Submit) => self.submit_query(ctx),
+            Some(UiAction::Back) => self.go_back(ctx),
+            Some(UiAction::Forward) => self.go_forward(ctx),
+            Some(UiAction::OpenHistory(index)) => self.open_history_entry(ctx, index),
+            Some(UiAction::Copy { text, what }) => self.copy_to_clipboard(&text, what),
+            None => {}
+        }
+
         // Auto-resize based on measured content
-        if !self.is_translating {
+        if !self.is_translating || self.show_history {
             if let Some(content_height) = inner_response.inner {
                 let has_resized_id = egui::Id::new("has_auto_resized");
                 let already_resized =
@@ -116,9 +570,8 @@ impl eframe::App for OcrApp {
                     let total_height = content_height + 32.0;
 
                     // Clamp between min and max
-                    let min_height = MIN_HEIGHT;
-                    let max_height = MAX_HEIGHT;
-                    let desired_height = total_height.clamp(min_height, max_height);
+                    let desired_height =
+                        total_height.clamp(self.min_height, self.max_height.max(self.min_height));
 
                     // Get current width
                     let current_width = ctx.screen_rect().width();
@@ -145,89 +598,526 @@ impl eframe::App for OcrApp {
         }
     }
 
-    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
-        [28.0 / 255.0, 28.0 / 255.0, 32.0 / 255.0, 1.0]
+    fn clear_color(&self, visuals: &egui::Visuals) -> [f32; 4] {
+        visuals.panel_fill.to_normalized_gamma_f32()
+    }
+}
+
+// --- Window ---
+
+/// Opens the result window and blocks until it is closed.
+/// Can be called repeatedly from the same (main) thread, as the daemon does.
+pub fn run_window(mut app: OcrApp, ui: &UiConfig) -> eframe::Result<()> {
+    app.min_height = ui.min_height;
+    app.max_height = ui.max_height;
+
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size([ui.width, 200.0])
+        .with_min_inner_size([400.0, 150.0])
+        .with_max_inner_size([ui.width.max(800.0), ui.max_height + 80.0])
+        .with_decorations(true)
+        .with_transparent(false)
+        .with_resizable(true);
+    if app.pinned {
+        viewport = viewport.with_window_level(egui::WindowLevel::AlwaysOnTop);
+        if let Some(position) = WindowState::load().pinned_position {
+            viewport = viewport.with_position(position);
+        }
+    }
+
+    eframe::run_native(
+        "Floating Dictionary",
+        native_options(viewport, ui),
+        Box::new({
+            let ui = ui.clone();
+            move |cc| {
+                setup_fonts_and_style(&cc.egui_ctx, &ui);
+                Ok(Box::new(app))
+            }
+        }),
+    )
+}
+
+/// Window options shared by the result and review windows. The configured
+/// theme is kept even when the desktop switches between light and dark.
+pub fn native_options(viewport: egui::ViewportBuilder, ui: &UiConfig) -> eframe::NativeOptions {
+    eframe::NativeOptions {
+        viewport,
+        follow_system_theme: false,
+        default_theme: match ui.theme {
+            Theme::Dark => eframe::Theme::Dark,
+            Theme::Light => eframe::Theme::Light,
+        },
+        ..Default::default()
+    }
+}
+
+/// Loads the Noto fonts of the known languages (see `languages::fonts`) and the text styles.
+pub fn setup_fonts_and_style(ctx: &egui::Context, ui: &UiConfig) {
+    let mut fonts = egui::FontDefinitions::default();
+    let mut font_family_list = Vec::new();
+    // User fonts come first, so they are used for every glyph they cover.
+    for (index, path) in ui.fonts.iter().enumerate() {
+        match std::fs::read(path) {
+            Ok(data) => {
+                let name = format!("user_font_{}", index);
+                fonts
+                    .font_data
+                    .insert(name.clone(), egui::FontData::from_owned(data));
+                font_family_list.push(name);
+            }
+            Err(e) => eprintln!("Failed to load the font {}: {}", path.display(), e),
+        }
+    }
+    for font in languages::fonts() {
+        fonts.font_data.insert(
+            font.name.to_owned(),
+            egui::FontData {
+                index: font.index,
+                ..egui::FontData::from_static(font.data)
+            },
+        );
+        font_family_list.push(font.name.to_owned());
     }
+    fonts
+        .families
+        .insert(egui::FontFamily::Proportional, font_family_list.clone());
+    fonts
+        .families
+        .insert(egui::FontFamily::Monospace, font_family_list);
+    ctx.set_fonts(fonts);
+    // Sizes relative to the 16pt body text.
+    let size = |scale: f32| egui::FontId::new(ui.font_size * scale, egui::FontFamily::Proportional);
+    let mut style = (*ctx.style()).clone();
+    style.text_styles = [
+        (egui::TextStyle::Body, size(1.0)),
+        (egui::TextStyle::Button, size(0.875)),
+        (egui::TextStyle::Small, size(0.75)),
+        (egui::TextStyle::Heading, size(1.5)),
+    ]
+    .into();
+    style.spacing.item_spacing = egui::Vec2::new(6.0, 6.0);
+    style.visuals = visuals(ui.theme);
+    ctx.set_style(style);
 }
 
 // --- Content Rendering ---
 
-fn render_content(ui: &mut egui::Ui, text: &str, data: &CombinedTranslationData) {
-    // 1. Search Term
-    ui.label(
-        egui::RichText::new(text)
-            .size(24.0)
-            .strong()
-            .color(egui::Color32::WHITE),
-    );
+/// Renders the editable search term with back/forward buttons.
+/// While the term is still the unedited OCR text, uncertain words are underlined.
+fn render_term(
+    ui: &mut egui::Ui,
+    query: &mut String,
+    ocr_result: Option<&OcrResult>,
+    can_go_back: bool,
+    can_go_forward: bool,
+) -> Option<UiAction> {
+    let mut action = None;
+    let ocr = ocr_result.filter(|ocr| ocr.text == *query);
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(can_go_back, egui::Button::new("<"))
+            .on_hover_text("Back (Alt+Left)")
+            .clicked()
+        {
+            action = Some(UiAction::Back);
+        }
+        if ui
+            .add_enabled(can_go_forward, egui::Button::new(">"))
+            .on_hover_text("Forward (Alt+Right)")
+            .clicked()
+        {
+            action = Some(UiAction::Forward);
+        }
+
+        let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+            let mut job = term_layout_job(text, ocr, ui.visuals());
+            job.wrap.max_width = wrap_width;
+            ui.fonts(|f| f.layout_job(job))
+        };
+        // Leave room for the Copy button and the buttons in the top-right corner.
+        let width = (ui.available_width() - 300.0).max(100.0);
+        let mut response = ui.add(
+            egui::TextEdit::singleline(query)
+                .desired_width(width)
+                .layouter(&mut layouter),
+        );
+        if let Some(ocr) = ocr.filter(|ocr| ocr.has_uncertain_words()) {
+            let uncertain = ocr
+                .words()
+                .filter(|word| word.is_uncertain())
+                .map(|word| format!("{} ({:.0}%)", word.text, word.confidence))
+                .collect::<Vec<_>>()
+                .join(", ");
+            response = response.on_hover_text(format!("Uncertain OCR words: {}", uncertain));
+        }
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            action = Some(UiAction::Submit);
+        }
+        if copy_button(ui, "Copy the text (Ctrl+Shift+O)") {
+            action = Some(UiAction::Copy {
+                text: query.clone(),
+                what: "text",
+            });
+        }
+    });
+
+    if let Some(ocr) = ocr {
+        let mut status = format!(
+            "OCR confidence {:.0}% · {:?} script",
+            ocr.mean_confidence, ocr.script
+        );
+        if !ocr.languages.is_empty() {
+            status.push_str(&format!(" · {}", ocr.languages.join("+")));
+        }
+        ui.label(
+            egui::RichText::new(status)
+                .size(12.0)
+                .color(ui.visuals().weak_text_color()),
+        );
+    }
     ui.add(egui::Separator::default().spacing(6.0));
 
-    // 2. Google Translate
-    render_section_header(
-        ui,
-        &format!("Google ({}):", data.target_lang.to_uppercase()),
-    );
-    render_bullet_point(ui, &data.google_translation);
-    ui.add_space(10.0);
+    action
+}
+
+/// Lays out the search term, underlining the OCR words Tesseract was unsure about.
+fn term_layout_job(
+    text: &str,
+    ocr: Option<&OcrResult>,
+    visuals: &egui::Visuals,
+) -> egui::text::LayoutJob {
+    let normal = egui::TextFormat {
+        font_id: egui::FontId::proportional(24.0),
+        color: visuals.strong_text_color(),
+        ..Default::default()
+    };
+    let uncertain = egui::TextFormat {
+        color: visuals.warn_fg_color,
+        underline: egui::Stroke::new(1.0, visuals.warn_fg_color),
+        ..normal.clone()
+    };
+
+    let mut job = egui::text::LayoutJob::default();
+    let mut cursor = 0;
+    for word in ocr.iter().flat_map(|ocr| ocr.words()) {
+        let shown = word.text_in_result();
+        let Some(offset) = text[cursor..].find(shown) else {
+            continue;
+        };
+        let start = cursor + offset;
+        let end = start + shown.len();
+        let format = if word.is_uncertain() {
+            &uncertain
+        } else {
+            &normal
+        };
+        job.append(&text[cursor..start], 0.0, normal.clone());
+        job.append(&text[start..end], 0.0, format.clone());
+        cursor = end;
+    }
+    job.append(&text[cursor..], 0.0, normal);
+    job
+}
+
+/// Renders the result sections, each with a Copy button.
+/// Returns the copy action when one was clicked.
+fn render_content(ui: &mut egui::Ui, data: &CombinedTranslationData) -> Option<UiAction> {
+    let mut action = None;
+    let mut copy = |text: String, what: &'static str| {
+        action = Some(UiAction::Copy { text, what });
+    };
+    let target = data.target_lang.to_uppercase();
+
+    // 1. Google Translate
+    if !data.google_translation.is_empty() {
+        if render_copyable_header(ui, &format!("Google ({}):", target)) {
+            copy(data.google_translation.clone(), "translation");
+        }
+        render_bullet_point(ui, &data.google_translation);
+        ui.add_space(10.0);
+    }
+
+    // 2. Other providers
+    for section in &data.sections {
+        ui.push_id(&section.provider, |ui| {
+            let header = format!("{} ({}):", section.provider, target);
+            if let Some(translation) = &section.translation {
+                if render_copyable_header(ui, &header) {
+                    copy(translation.clone(), "translation");
+                }
+                render_bullet_point(ui, translation);
+            } else {
+                render_section_header(ui, &header);
+            }
+            for (index, item) in section.entries.iter().enumerate() {
+                if render_copyable(ui, index, |ui| render_translation_item(ui, item)) {
+                    copy(translation_item_text(item), "definition");
+                }
+            }
+        });
+        ui.add_space(10.0);
+    }
 
     // 3. Longdo Dict
     if let Some(longdo) = &data.longdo_data {
         if !longdo.translations.is_empty() {
             render_section_header(ui, "Longdo Dict:");
-            for item in &longdo.translations {
-                render_translation_item(ui, item);
-            }
+            ui.push_id("longdo", |ui| {
+                for (index, item) in longdo.translations.iter().enumerate() {
+                    if render_copyable(ui, index, |ui| render_translation_item(ui, item)) {
+                        copy(translation_item_text(item), "definition");
+                    }
+                }
+            });
             ui.add_space(10.0);
         }
 
         // 4. Examples
         if !longdo.examples.is_empty() {
             render_section_header(ui, "Example Sentences (Longdo):");
-            for ex in longdo.examples.iter().take(2) {
-                render_example_item(ui, ex, &data.source_lang, &data.target_lang);
-            }
+            ui.push_id("examples", |ui| {
+                for (index, ex) in longdo.examples.iter().take(2).enumerate() {
+                    let clicked = render_copyable(ui, index, |ui| {
+                        render_example_item(ui, ex, &data.source_lang, &data.target_lang)
+                    });
+                    if clicked {
+                        copy(format!("{}\n{}", ex.en, ex.th), "example");
+                    }
+                }
+            });
         }
     }
 
+    // 5. Providers that failed
+    for error in &data.errors {
+        ui.label(
+            egui::RichText::new(format!("{}: {}", error.provider, error.message))
+                .small()
+                .color(ui.visuals().error_fg_color),
+        );
+    }
+
     // Add bottom padding to prevent text from being cut off
     ui.add_space(8.0);
+    action
+}
+
+/// Lists past lookups with a search field and a language filter.
+/// Returns the content height and the entry that was clicked, if any.
+fn render_history(
+    ui: &mut egui::Ui,
+    entries: &[HistoryEntry],
+    filter: &mut HistoryFilter,
+    max_height: f32,
+) -> (f32, Option<UiAction>) {
+    let mut action = None;
+
+    render_section_header(ui, "History:");
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(&mut filter.search)
+                .hint_text("Search")
+                .desired_width(200.0),
+        );
+
+        let mut langs: Vec<&str> = entries
+            .iter()
+            .flat_map(|entry| [entry.source_lang.as_str(), entry.target_lang.as_str()])
+            .filter(|lang| !lang.is_empty())
+            .collect();
+        langs.sort_unstable();
+        langs.dedup();
+
+        let selected = if filter.lang.is_empty() {
+            "All languages".to_string()
+        } else {
+            filter.lang.clone()
+        };
+        egui::ComboBox::from_id_source("history_lang")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter.lang, String::new(), "All languages");
+                for lang in langs {
+                    ui.selectable_value(&mut filter.lang, lang.to_string(), lang);
+                }
+            });
+    });
+    ui.add(egui::Separator::default().spacing(6.0));
+
+    let scroll_response = egui::ScrollArea::vertical()
+        .max_height(max_height - 120.0)
+        .show(ui, |ui| {
+            ui.set_max_width(ui.available_width());
+
+            let mut shown = 0;
+            for (index, entry) in entries.iter().enumerate() {
+                if !filter.matches(entry) {
+                    continue;
+                }
+                shown += 1;
+
+                let summary = match entry.data.summary() {
+                    "" => entry.text.clone(),
+                    translation => format!("{}  →  {}", entry.text, translation),
+                };
+                let clicked = ui
+                    .add(
+                        egui::Label::new(
+                            egui::RichText::new(summary).color(ui.visuals().text_color()),
+                        )
+                        .wrap()
+                        .sense(egui::Sense::click()),
+                    )
+                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                    .clicked();
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} · {} → {}",
+                        history::format_timestamp(entry.timestamp),
+                        entry.source_lang,
+                        entry.target_lang
+                    ))
+                    .size(12.0)
+                    .color(ui.visuals().weak_text_color()),
+                );
+                ui.add_space(4.0);
+
+                if clicked {
+                    action = Some(UiAction::OpenHistory(index));
+                }
+            }
+
+            if shown == 0 {
+                ui.label(
+                    egui::RichText::new("No matching lookups.")
+                        .color(ui.visuals().weak_text_color()),
+                );
+            }
+            ui.min_rect().height()
+        });
+
+    (scroll_response.inner + 80.0, action)
+}
+
+/// Shows why the translation failed. Returns `true` when Retry was clicked.
+fn render_error(ui: &mut egui::Ui, error: &TranslationError) -> bool {
+    render_section_header(ui, "Translation failed:");
+    ui.label(egui::RichText::new(error.to_string()).color(ui.visuals().error_fg_color));
+    let hint = match error {
+        TranslationError::Network(_) => {
+            "Check your internet connection, or add the offline stardict provider."
+        }
+        TranslationError::Timeout => {
+            "The service may be slow right now; try again or raise --timeout-secs."
+        }
+        TranslationError::Parse(_) => "The service may have changed its response format.",
+        TranslationError::RateLimited => "Wait a moment before trying again.",
+        TranslationError::NoResults => "None of the enabled providers knew this text.",
+    };
+    ui.label(
+        egui::RichText::new(hint)
+            .size(12.0)
+            .color(ui.visuals().weak_text_color()),
+    );
+    ui.add_space(10.0);
+
+    ui.button("Retry").clicked()
 }
 
 // --- UI Helper Functions ---
 
-fn setup_visuals(ctx: &egui::Context) {
-    let mut visuals = egui::Visuals::dark();
+/// Lets the window fit its content again the next time a result is shown.
+fn allow_auto_resize(ctx: &egui::Context) {
+    ctx.memory_mut(|m| m.data.remove::<bool>(egui::Id::new("has_auto_resized")));
+}
+
+/// A small Copy button. Returns `true` when clicked.
+fn copy_button(ui: &mut egui::Ui, hover: &str) -> bool {
+    ui.small_button("Copy").on_hover_text(hover).clicked()
+}
+
+/// A section header followed by a Copy button. Returns `true` when the button was clicked.
+fn render_copyable_header(ui: &mut egui::Ui, title: &str) -> bool {
+    ui.horizontal(|ui| {
+        render_section_header(ui, title);
+        copy_button(ui, "Copy this translation")
+    })
+    .inner
+}
+
+/// Renders an item with a Copy button in front of it. Returns `true` when the button was clicked.
+fn render_copyable(ui: &mut egui::Ui, index: usize, render: impl FnOnce(&mut egui::Ui)) -> bool {
+    ui.push_id(index, |ui| {
+        ui.horizontal_top(|ui| {
+            let clicked = copy_button(ui, "Copy");
+            ui.vertical(render);
+            clicked
+        })
+        .inner
+    })
+    .inner
+}
+
+fn translation_item_text(item: &TranslationItem) -> String {
+    format!("{} [{}] {}", item.word, item.pos, item.translation)
+}
+
+/// The colors for a theme. The rendering functions take their colors from
+/// these visuals: `text_color` for content, `strong_text_color` for headings,
+/// `weak_text_color` for labels, `hyperlink_color` for dictionary words.
+fn visuals(theme: Theme) -> egui::Visuals {
+    let mut visuals = match theme {
+        Theme::Dark => {
+            let background = egui::Color32::from_rgb(28, 28, 32);
+            let mut visuals = egui::Visuals::dark();
+            visuals.panel_fill = background;
+            visuals.window_fill = background;
+            visuals.extreme_bg_color = background;
+            visuals.widgets.noninteractive.fg_stroke.color = egui::Color32::from_gray(230);
+            visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(60, 100, 150);
+            visuals.widgets.active.bg_fill = egui::Color32::from_rgb(70, 110, 170);
+            visuals.hyperlink_color = egui::Color32::from_rgb(160, 220, 255);
+            visuals.warn_fg_color = egui::Color32::from_rgb(255, 190, 90);
+            visuals.error_fg_color = egui::Color32::from_rgb(255, 140, 120);
+            visuals
+        }
+        Theme::Light => {
+            let background = egui::Color32::from_rgb(250, 250, 248);
+            let mut visuals = egui::Visuals::light();
+            visuals.panel_fill = background;
+            visuals.window_fill = background;
+            visuals.widgets.noninteractive.fg_stroke.color = egui::Color32::from_gray(30);
+            visuals.hyperlink_color = egui::Color32::from_rgb(20, 90, 170);
+            visuals.warn_fg_color = egui::Color32::from_rgb(190, 100, 0);
+            visuals.error_fg_color = egui::Color32::from_rgb(190, 40, 30);
+            visuals
+        }
+    };
     visuals.window_shadow = egui::epaint::Shadow::NONE;
-    visuals.panel_fill = egui::Color32::from_rgb(28, 28, 32);
-    visuals.window_fill = egui::Color32::from_rgb(28, 28, 32);
-    visuals.extreme_bg_color = egui::Color32::from_rgb(28, 28, 32);
-    // visuals.widgets.inactive.bg_fill = egui::Color32::from_rgb(50, 80, 120);
-    visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(60, 100, 150);
-    visuals.widgets.active.bg_fill = egui::Color32::from_rgb(70, 110, 170);
-    ctx.set_visuals(visuals);
+    visuals
 }
 
-fn render_section_header(ui: &mut egui::Ui, title: &str) {
+pub fn render_section_header(ui: &mut egui::Ui, title: &str) {
     ui.label(
         egui::RichText::new(title)
             .size(18.0)
             .underline()
             .strong()
-            .color(egui::Color32::from_gray(220)),
+            .color(ui.visuals().strong_text_color()),
     );
     ui.add_space(2.0);
 }
 
-fn render_bullet_point(ui: &mut egui::Ui, text: &str) {
+pub fn render_bullet_point(ui: &mut egui::Ui, text: &str) {
     ui.horizontal(|ui| {
         ui.label("•");
-        ui.add(
-            egui::Label::new(egui::RichText::new(text).color(egui::Color32::from_gray(240))).wrap(),
-        );
+        ui.add(egui::Label::new(egui::RichText::new(text).color(ui.visuals().text_color())).wrap());
     });
 }
 
-fn render_translation_item(ui: &mut egui::Ui, item: &TranslationItem) {
+pub fn render_translation_item(ui: &mut egui::Ui, item: &TranslationItem) {
     ui.horizontal(|ui| {
         ui.label("•");
         ui.vertical(|ui| {
@@ -235,24 +1125,24 @@ fn render_translation_item(ui: &mut egui::Ui, item: &TranslationItem) {
                 ui.label(
                     egui::RichText::new(&item.word)
                         .strong()
-                        .color(egui::Color32::from_rgb(160, 220, 255)),
+                        .color(ui.visuals().hyperlink_color),
                 );
                 ui.label(
                     egui::RichText::new(format!("[{}]", item.pos))
                         .italics()
-                        .color(egui::Color32::from_gray(180)),
+                        .color(ui.visuals().weak_text_color()),
                 );
             });
             ui.label(
                 egui::RichText::new(format!("{} ({})", item.translation, item.dictionary))
-                    .color(egui::Color32::from_gray(230)),
+                    .color(ui.visuals().text_color()),
             );
         });
     });
     ui.add_space(4.0);
 }
 
-fn render_example_item(
+pub fn render_example_item(
     ui: &mut egui::Ui,
     item: &ExampleItem,
     source_lang: &str,
@@ -263,9 +1153,9 @@ fn render_example_item(
         ui.label(
             egui::RichText::new(format!(" {}:", source_lang.to_uppercase()))
                 .italics()
-                .color(egui::Color32::from_gray(180)),
+                .color(ui.visuals().weak_text_color()),
         );
-        ui.label(egui::RichText::new(&item.en).color(egui::Color32::from_gray(210)));
+        ui.label(egui::RichText::new(&item.en).color(ui.visuals().text_color()));
     });
 
     ui.horizontal_wrapped(|ui| {
@@ -274,9 +1164,9 @@ fn render_example_item(
         ui.label(
             egui::RichText::new(format!("-> {}:", target_lang.to_uppercase()))
                 .italics()
-                .color(egui::Color32::from_gray(180)),
+                .color(ui.visuals().weak_text_color()),
         );
-        ui.label(egui::RichText::new(&item.th).color(egui::Color32::from_gray(230)));
+        ui.label(egui::RichText::new(&item.th).color(ui.visuals().text_color()));
     });
     ui.add_space(8.0);
 }
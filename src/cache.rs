@@ -0,0 +1,376 @@
+use crate::history::History;
+use crate::paths;
+use crate::translation::{CombinedTranslationData, TranslateResult, Translator};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::runtime::Handle;
+
+/// An over-full cache is trimmed to this share of `max_entries`, so the files
+/// only need to be listed and sorted once every many lookups.
+const EVICT_TO_PERCENT: usize = 90;
+
+// --- Data Structures ---
+
+/// An on-disk cache of translation results, one JSON file per lookup.
+/// Entries are keyed by the text, source language, target language and provider list.
+#[derive(Debug, Clone)]
+pub struct TranslationCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_entries: usize,
+    /// Entries that can still be stored before the files are counted again.
+    /// Shared by the clones; other processes writing to the cache only make
+    /// the limit a little soft.
+    room: Arc<AtomicUsize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    created_at: u64, // Seconds since the Unix epoch
+    text: String,
+    source: String,
+    target: String,
+    providers: String, // See `Translator::id`
+    data: CombinedTranslationData,
+}
+
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub expired: usize,
+    pub total_bytes: u64,
+}
+
+// --- Cache Implementation ---
+
+impl TranslationCache {
+    /// Opens the cache in the application's data directory, creating it if needed.
+    pub fn open(ttl: Duration, max_entries: usize) -> io::Result<Self> {
+        Self::in_dir(paths::app_data_dir()?.join("cache"), ttl, max_entries)
+    }
+
+    fn in_dir(dir: PathBuf, ttl: Duration, max_entries: usize) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            ttl,
+            max_entries,
+            room: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Looks up a cached result. The flag is `false` when the entry is older than the TTL;
+    /// such entries are only used when the network lookup fails.
+    pub fn get(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+        providers: &str,
+    ) -> Option<(CombinedTranslationData, bool)> {
+        let path = self.entry_path(text, source, target, providers);
+        let contents = fs::read_to_string(path).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&contents).ok()?;
+
+        // Guard against hash collisions.
+        if entry.text != text.trim()
+            || entry.source != source
+            || entry.target != target
+            || entry.providers != providers
+        {
+            return None;
+        }
+
+        let fresh = now_secs().saturating_sub(entry.created_at) <= self.ttl.as_secs();
+        Some((entry.data, fresh))
+    }
+
+    /// Stores a result and evicts the oldest entries if the cache is over its size limit.
+    pub fn put(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+        providers: &str,
+        data: &CombinedTranslationData,
+    ) -> io::Result<()> {
+        let entry = CacheEntry {
+            created_at: now_secs(),
+            text: text.trim().to_string(),
+            source: source.to_string(),
+            target: target.to_string(),
+            providers: providers.to_string(),
+            data: data.clone(),
+        };
+        fs::write(
+            self.entry_path(text, source, target, providers),
+            serde_json::to_vec(&entry)?,
+        )?;
+        self.evict()
+    }
+
+    /// Removes every cached entry and returns how many were removed.
+    pub fn clear(&self) -> io::Result<usize> {
+        let files = self.entry_files()?;
+        for (path, _) in &files {
+            fs::remove_file(path)?;
+        }
+        Ok(files.len())
+    }
+
+    pub fn stats(&self) -> io::Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        for (path, _) in self.entry_files()? {
+            stats.entries += 1;
+            stats.total_bytes += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+
+            let expired = fs::read_to_string(&path)
+                .ok()
+                .and_then(|contents| serde_json::from_str::<CacheEntry>(&contents).ok())
+                .is_none_or(|entry| {
+                    now_secs().saturating_sub(entry.created_at) > self.ttl.as_secs()
+                });
+            if expired {
+                stats.expired += 1;
+            }
+        }
+        Ok(stats)
+    }
+
+    fn entry_path(&self, text: &str, source: &str, target: &str, providers: &str) -> PathBuf {
+        let key = format!("{}\0{}\0{}\0{}", text.trim(), source, target, providers);
+        self.dir
+            .join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+    }
+
+    /// Lists the cache files with their modification times.
+    fn entry_files(&self) -> io::Result<Vec<(PathBuf, SystemTime)>> {
+        let mut files = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if is_entry_file(&path) {
+                let modified = fs::metadata(&path)?.modified()?;
+                files.push((path, modified));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Called after every put. The files are only counted once the room left
+    /// from the last count is used up, and only looked at when there are too many.
+    fn evict(&self) -> io::Result<()> {
+        let room = self.room.load(Ordering::Relaxed);
+        if room > 0 {
+            self.room.store(room - 1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let count = fs::read_dir(&self.dir)?
+            .filter_map(Result::ok)
+            .filter(|entry| is_entry_file(&entry.path()))
+            .count();
+        if count <= self.max_entries {
+            self.room.store(self.max_entries - count, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let mut files = self.entry_files()?;
+        files.sort_by_key(|(_, modified)| *modified);
+        let keep = self.max_entries * EVICT_TO_PERCENT / 100;
+        let excess = files.len().saturating_sub(keep);
+        for (path, _) in files.into_iter().take(excess) {
+            let _ = fs::remove_file(path);
+        }
+        self.room.store(self.max_entries - keep, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Translates text, serving fresh results from the cache when possible.
+/// If the network lookup fails, an expired cache entry is returned instead of the error,
+/// so previously seen words keep working offline.
+pub async fn translate_cached(
+    translator: &Translator,
+    cache: Option<&TranslationCache>,
+    text: &str,
+    source: &str,
+    target: &str,
+) -> TranslateResult<CombinedTranslationData> {
+    let Some(cache) = cache else {
+        return translator.translate(text, source, target).await;
+    };
+
+    let providers = translator.id();
+    let cached = cache.get(text, source, target, &providers);
+    if let Some((data, true)) = cached {
+        return Ok(data);
+    }
+
+    match translator.translate(text, source, target).await {
+        Ok(data) => {
+            // A result some provider failed on is incomplete; it is not kept for
+            // the whole TTL, so the next lookup asks that provider again.
+            if data.errors.is_empty() {
+                // A failed cache write should never fail the lookup itself.
+                let _ = cache.put(text, source, target, &providers, &data);
+            }
+            Ok(data)
+        }
+        Err(e) => match cached {
+            Some((stale, _)) => Ok(stale),
+            None => Err(e),
+        },
+    }
+}
+
+/// Everything needed to look text up: the providers, the cache and the history.
+/// The UI keeps one around so it can retry a failed lookup.
+#[derive(Clone)]
+pub struct Lookup {
+    pub translator: Translator,
+    pub cache: Option<TranslationCache>,
+    pub history: Option<History>,
+    pub target: String,
+    /// The program's Tokio runtime. Lookups from other threads run on it, because
+    /// the HTTP client's pooled connections belong to the runtime that opened them.
+    pub runtime: Handle,
+}
+
+impl Lookup {
+    /// Translates `text` (through the cache) and records the result in the history.
+    pub async fn translate(&self, text: &str) -> TranslateResult<CombinedTranslationData> {
+        let data = translate_cached(
+            &self.translator,
+            self.cache.as_ref(),
+            text,
+            "auto", // Always use auto-detection for the Google Translate source language
+            &self.target,
+        )
+        .await?;
+        if let Some(history) = &self.history {
+            // A failed history write should never fail the lookup itself.
+            let _ = history.record(text, &data);
+        }
+        Ok(data)
+    }
+
+    /// Translates `text` on the runtime in the background; the result arrives on
+    /// the returned channel.
+    pub fn spawn(&self, text: &str) -> Receiver<TranslateResult<CombinedTranslationData>> {
+        let (tx, rx) = channel();
+        let lookup = self.clone();
+        let text = text.to_string();
+        self.runtime.spawn(async move {
+            let _ = tx.send(lookup.translate(&text).await);
+        });
+        rx
+    }
+}
+
+// --- Helper Functions ---
+
+fn is_entry_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 64-bit FNV-1a. Used instead of `DefaultHasher` because file names must
+/// stay the same across Rust versions.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn cache(name: &str, max_entries: usize) -> TranslationCache {
+        TranslationCache::in_dir(paths::test_dir(name), DAY, max_entries).unwrap()
+    }
+
+    #[test]
+    fn key_covers_text_languages_and_providers() {
+        let cache = cache("cache-key", 10);
+        let path = cache.entry_path("hello", "auto", "th", "google");
+        assert_eq!(path, cache.entry_path("  hello ", "auto", "th", "google"));
+        assert_ne!(path, cache.entry_path("hello", "en", "th", "google"));
+        assert_ne!(path, cache.entry_path("hello", "auto", "ja", "google"));
+        assert_ne!(
+            path,
+            cache.entry_path("hello", "auto", "th", "google,longdo")
+        );
+    }
+
+    #[test]
+    fn put_then_get_is_fresh() {
+        let cache = cache("cache-get", 10);
+        let data = CombinedTranslationData::sample("hello", "สวัสดี");
+        cache.put("hello", "auto", "th", "google", &data).unwrap();
+
+        let (cached, fresh) = cache.get("hello", "auto", "th", "google").unwrap();
+        assert!(fresh);
+        assert_eq!(cached.google_translation, "สวัสดี");
+        assert!(cache.get("hello", "auto", "th", "longdo").is_none());
+    }
+
+    #[test]
+    fn entries_older_than_the_ttl_are_stale() {
+        let cache = cache("cache-ttl", 10);
+        let entry = CacheEntry {
+            created_at: now_secs() - 2 * DAY.as_secs(),
+            text: "hello".to_string(),
+            source: "auto".to_string(),
+            target: "th".to_string(),
+            providers: "google".to_string(),
+            data: CombinedTranslationData::sample("hello", "สวัสดี"),
+        };
+        fs::write(
+            cache.entry_path("hello", "auto", "th", "google"),
+            serde_json::to_vec(&entry).unwrap(),
+        )
+        .unwrap();
+
+        let (_, fresh) = cache.get("hello", "auto", "th", "google").unwrap();
+        assert!(!fresh);
+        assert_eq!(cache.stats().unwrap().expired, 1);
+    }
+
+    #[test]
+    fn eviction_trims_to_ninety_percent() {
+        let cache = cache("cache-evict", 10);
+        let data = CombinedTranslationData::sample("word", "คำ");
+        for i in 0..10 {
+            cache
+                .put(&format!("word {}", i), "auto", "th", "google", &data)
+                .unwrap();
+        }
+        assert_eq!(cache.stats().unwrap().entries, 10);
+
+        cache
+            .put("one too many", "auto", "th", "google", &data)
+            .unwrap();
+        assert_eq!(cache.stats().unwrap().entries, 9);
+    }
+}
@@ -0,0 +1,471 @@
+use crate::portal;
+use clap::ValueEnum;
+use futures_util::future::LocalBoxFuture;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use x11rb::connection::Connection as X11Connection;
+use x11rb::protocol::xproto::{self, ConnectionExt as _};
+use x11rb::protocol::Event;
+use zbus::zvariant::{Str, Value};
+use zbus::Connection;
+
+type CaptureResult = Result<Vec<u8>, Box<dyn std::error::Error>>;
+
+/// A way of letting the user select a screen region and grabbing its pixels.
+pub trait CaptureBackend {
+    /// The name used for this backend on the command line.
+    fn name(&self) -> &'static str;
+
+    /// Whether this backend is expected to work in the current session.
+    /// Used by the automatic probe; an explicitly chosen backend is always tried.
+    fn is_available(&self) -> bool;
+
+    /// Lets the user select a region and returns the encoded image bytes.
+    fn capture(&self) -> LocalBoxFuture<'_, CaptureResult>;
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureBackendKind {
+    /// Pick the first available backend (Spectacle, grim, X11, then the portal).
+    Auto,
+    /// KDE Spectacle.
+    Spectacle,
+    /// The Freedesktop Screenshot portal (GNOME and most Wayland desktops).
+    Portal,
+    /// grim + slurp on wlroots compositors (Sway, Hyprland, ...).
+    Grim,
+    /// A built-in region selector for X11 sessions.
+    X11,
+}
+
+impl CaptureBackendKind {
+    fn backend(self) -> Option<Box<dyn CaptureBackend>> {
+        match self {
+            CaptureBackendKind::Auto => None,
+            CaptureBackendKind::Spectacle => Some(Box::new(SpectacleBackend)),
+            CaptureBackendKind::Portal => Some(Box::new(PortalBackend)),
+            CaptureBackendKind::Grim => Some(Box::new(GrimBackend)),
+            CaptureBackendKind::X11 => Some(Box::new(X11Backend)),
+        }
+    }
+}
+
+/// Returns the backend to use for `kind`, probing the session when it is `Auto`.
+pub fn select_backend(
+    kind: CaptureBackendKind,
+) -> Result<Box<dyn CaptureBackend>, Box<dyn std::error::Error>> {
+    if let Some(backend) = kind.backend() {
+        return Ok(backend);
+    }
+
+    // Probe order: the most specific tools first, the portal as the fallback.
+    let candidates: Vec<Box<dyn CaptureBackend>> = vec![
+        Box::new(SpectacleBackend),
+        Box::new(GrimBackend),
+        Box::new(X11Backend),
+        Box::new(PortalBackend),
+    ];
+    candidates
+        .into_iter()
+        .find(|backend| backend.is_available())
+        .ok_or_else(|| {
+            "No screen capture backend is available. Install spectacle, grim + slurp, \
+             or xdg-desktop-portal, or pass --image."
+                .into()
+        })
+}
+
+/// Captures a screen region with the chosen backend and returns the encoded image bytes.
+pub async fn capture_image(kind: CaptureBackendKind) -> CaptureResult {
+    let backend = select_backend(kind)?;
+    backend
+        .capture()
+        .await
+        .map_err(|e| format!("{} capture: {}", backend.name(), e).into())
+}
+
+/// The desktop's scale factor as the toolkits see it (`GDK_SCALE`, then
+/// `QT_SCALE_FACTOR`), or 1 when neither is set.
+pub fn scale_factor() -> f32 {
+    ["GDK_SCALE", "QT_SCALE_FACTOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok()?.trim().parse::<f32>().ok())
+        .find(|scale| *scale > 0.0)
+        .unwrap_or(1.0)
+}
+
+// --- Helper Functions ---
+
+fn current_desktop() -> String {
+    env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .to_uppercase()
+}
+
+fn command_exists(name: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(name).is_file()))
+        .unwrap_or(false)
+}
+
+fn temp_capture_path() -> PathBuf {
+    // Generate a random file name for the temporary screenshot.
+    let mut rng = rand::rng();
+    let temp_file_name: String = (0..12)
+        .map(|_| rng.sample(rand::distr::Alphanumeric) as char)
+        .collect();
+    env::temp_dir().join(format!("capture_{}.png", temp_file_name))
+}
+
+/// Reads a temporary screenshot file and removes it immediately afterwards.
+fn read_and_remove(path: &Path) -> CaptureResult {
+    let image_data = fs::read(path)?;
+    let _ = fs::remove_file(path);
+    Ok(image_data)
+}
+
+// --- Spectacle (KDE) ---
+
+/// Captures a screen region using KDE's Spectacle tool.
+/// This is a command-line approach that is often more reliable on KDE Plasma.
+pub struct SpectacleBackend;
+
+impl CaptureBackend for SpectacleBackend {
+    fn name(&self) -> &'static str {
+        "spectacle"
+    }
+
+    fn is_available(&self) -> bool {
+        current_desktop().contains("KDE") && command_exists("spectacle")
+    }
+
+    fn capture(&self) -> LocalBoxFuture<'_, CaptureResult> {
+        Box::pin(async {
+            let temp_path = temp_capture_path();
+            let temp_path_str = temp_path
+                .to_str()
+                .ok_or("Failed to create a temporary file path.")?;
+
+            // Execute Spectacle in region selection mode.
+            // -b: non-GUI, background mode
+            // -n: no notification
+            // -r: region mode
+            // -o: output file
+            let output = Command::new("spectacle")
+                .args(["-b", "-n", "-r", "-o", temp_path_str])
+                .output()?;
+
+            if !output.status.success() {
+                // This typically happens if the user presses 'Esc' to cancel the screenshot.
+                return Err("Screenshot cancelled by user.".into());
+            }
+
+            read_and_remove(&temp_path)
+        })
+    }
+}
+
+// --- grim + slurp (wlroots) ---
+
+/// Captures a screen region on wlroots compositors, using `slurp` to select
+/// the region and `grim` to grab it.
+pub struct GrimBackend;
+
+impl CaptureBackend for GrimBackend {
+    fn name(&self) -> &'static str {
+        "grim"
+    }
+
+    fn is_available(&self) -> bool {
+        const WLROOTS_DESKTOPS: [&str; 5] = ["SWAY", "HYPRLAND", "RIVER", "WAYFIRE", "LABWC"];
+
+        let on_wlroots = env::var_os("SWAYSOCK").is_some()
+            || env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some()
+            || WLROOTS_DESKTOPS
+                .iter()
+                .any(|desktop| current_desktop().contains(desktop));
+
+        env::var_os("WAYLAND_DISPLAY").is_some()
+            && on_wlroots
+            && command_exists("grim")
+            && command_exists("slurp")
+    }
+
+    fn capture(&self) -> LocalBoxFuture<'_, CaptureResult> {
+        Box::pin(async {
+            // slurp prints the selected geometry as "x,y wxh" and exits non-zero on 'Esc'.
+            let selection = Command::new("slurp").output()?;
+            if !selection.status.success() {
+                return Err("Screenshot cancelled by user.".into());
+            }
+            let geometry = String::from_utf8(selection.stdout)?.trim().to_string();
+
+            // '-' makes grim write the PNG to stdout.
+            let output = Command::new("grim").args(["-g", &geometry, "-"]).output()?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("grim failed: {}", stderr.trim()).into());
+            }
+
+            Ok(output.stdout)
+        })
+    }
+}
+
+// --- X11 ---
+
+/// Captures a screen region on X11 with a built-in rubber-band selector.
+/// The user drags a rectangle with the mouse; 'Esc' or a right click cancels.
+pub struct X11Backend;
+
+impl CaptureBackend for X11Backend {
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+
+    fn is_available(&self) -> bool {
+        let session_type = env::var("XDG_SESSION_TYPE").unwrap_or_default();
+        env::var_os("DISPLAY").is_some()
+            && env::var_os("WAYLAND_DISPLAY").is_none()
+            && session_type != "wayland"
+    }
+
+    fn capture(&self) -> LocalBoxFuture<'_, CaptureResult> {
+        Box::pin(async { capture_x11() })
+    }
+}
+
+fn capture_x11() -> CaptureResult {
+    // The X cursor font glyph for a crosshair and the keycode-independent keysym for Escape.
+    const XC_CROSSHAIR: u16 = 34;
+    const XK_ESCAPE: u32 = 0xff1b;
+
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let screen = &conn.setup().roots[screen_num];
+    let root = screen.root;
+
+    // A crosshair cursor makes it obvious that a selection is in progress.
+    let font = conn.generate_id()?;
+    conn.open_font(font, b"cursor")?;
+    let cursor = conn.generate_id()?;
+    conn.create_glyph_cursor(
+        cursor,
+        font,
+        font,
+        XC_CROSSHAIR,
+        XC_CROSSHAIR + 1,
+        0xffff,
+        0xffff,
+        0xffff,
+        0,
+        0,
+        0,
+    )?;
+
+    // XOR drawing on the root window lets us erase the rectangle by drawing it again.
+    let gc = conn.generate_id()?;
+    conn.create_gc(
+        gc,
+        root,
+        &xproto::CreateGCAux::new()
+            .function(xproto::GX::XOR)
+            .foreground(screen.white_pixel ^ screen.black_pixel)
+            .line_width(2)
+            .subwindow_mode(xproto::SubwindowMode::INCLUDE_INFERIORS),
+    )?;
+
+    let pointer = conn
+        .grab_pointer(
+            false,
+            root,
+            xproto::EventMask::BUTTON_PRESS
+                | xproto::EventMask::BUTTON_RELEASE
+                | xproto::EventMask::POINTER_MOTION,
+            xproto::GrabMode::ASYNC,
+            xproto::GrabMode::ASYNC,
+            x11rb::NONE,
+            cursor,
+            x11rb::CURRENT_TIME,
+        )?
+        .reply()?;
+    if pointer.status != xproto::GrabStatus::SUCCESS {
+        return Err("Could not grab the mouse pointer for region selection.".into());
+    }
+    conn.grab_keyboard(
+        false,
+        root,
+        x11rb::CURRENT_TIME,
+        xproto::GrabMode::ASYNC,
+        xproto::GrabMode::ASYNC,
+    )?
+    .reply()?;
+
+    // Find the keycodes that produce Escape so the user can cancel.
+    let setup = conn.setup();
+    let keyboard = conn
+        .get_keyboard_mapping(setup.min_keycode, setup.max_keycode - setup.min_keycode + 1)?
+        .reply()?;
+    let per_keycode = keyboard.keysyms_per_keycode.max(1) as usize;
+    let escape_keycodes: Vec<u8> = keyboard
+        .keysyms
+        .chunks(per_keycode)
+        .enumerate()
+        .filter(|(_, syms)| syms.contains(&XK_ESCAPE))
+        .map(|(i, _)| setup.min_keycode + i as u8)
+        .collect();
+    conn.flush()?;
+
+    let mut start: Option<(i16, i16)> = None;
+    let mut drawn: Option<xproto::Rectangle> = None;
+    let selection = loop {
+        match conn.wait_for_event()? {
+            Event::ButtonPress(event) if event.detail == 1 => {
+                start = Some((event.root_x, event.root_y));
+            }
+            Event::ButtonPress(_) => break None,
+            Event::MotionNotify(event) => {
+                if let Some(origin) = start {
+                    let rect = normalize_rect(origin, (event.root_x, event.root_y));
+                    if let Some(old) = drawn.take() {
+                        conn.poly_rectangle(root, gc, &[old])?;
+                    }
+                    conn.poly_rectangle(root, gc, &[rect])?;
+                    drawn = Some(rect);
+                    conn.flush()?;
+                }
+            }
+            Event::ButtonRelease(event) if event.detail == 1 => {
+                if let Some(origin) = start {
+                    break Some(normalize_rect(origin, (event.root_x, event.root_y)));
+                }
+            }
+            Event::KeyPress(event) if escape_keycodes.contains(&event.detail) => break None,
+            _ => {}
+        }
+    };
+
+    // Erase the rubber band and release the grabs before grabbing the pixels.
+    if let Some(old) = drawn {
+        conn.poly_rectangle(root, gc, &[old])?;
+    }
+    conn.ungrab_pointer(x11rb::CURRENT_TIME)?;
+    conn.ungrab_keyboard(x11rb::CURRENT_TIME)?;
+    conn.free_gc(gc)?;
+    conn.free_cursor(cursor)?;
+    conn.close_font(font)?;
+    conn.flush()?;
+
+    let rect = selection.ok_or("Screenshot cancelled by user.")?;
+    if rect.width < 2 || rect.height < 2 {
+        return Err("Selected region is too small.".into());
+    }
+
+    let image = conn
+        .get_image(
+            xproto::ImageFormat::Z_PIXMAP,
+            root,
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            !0,
+        )?
+        .reply()?;
+
+    let bits_per_pixel = setup
+        .pixmap_formats
+        .iter()
+        .find(|format| format.depth == image.depth)
+        .map(|format| format.bits_per_pixel)
+        .unwrap_or(0);
+    if bits_per_pixel != 32 || setup.image_byte_order != xproto::ImageOrder::LSB_FIRST {
+        return Err(format!(
+            "Unsupported X11 pixel format (depth {}, {} bpp).",
+            image.depth, bits_per_pixel
+        )
+        .into());
+    }
+
+    Ok(encode_ppm(&image.data, rect.width, rect.height))
+}
+
+/// Builds a rectangle from two corner points, regardless of drag direction.
+fn normalize_rect(a: (i16, i16), b: (i16, i16)) -> xproto::Rectangle {
+    xproto::Rectangle {
+        x: a.0.min(b.0),
+        y: a.1.min(b.1),
+        width: a.0.abs_diff(b.0),
+        height: a.1.abs_diff(b.1),
+    }
+}
+
+/// Encodes 32-bit BGRX pixels as a binary PPM, which Leptonica reads natively.
+fn encode_ppm(bgrx: &[u8], width: u16, height: u16) -> Vec<u8> {
+    let header = format!("P6\n{} {}\n255\n", width, height);
+    let mut ppm = Vec::with_capacity(header.len() + width as usize * height as usize * 3);
+    ppm.extend_from_slice(header.as_bytes());
+    for pixel in bgrx.chunks_exact(4) {
+        ppm.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+    }
+    ppm
+}
+
+// --- Freedesktop portal ---
+
+/// Captures a screen region using the Freedesktop Screenshot portal (DBus).
+/// This is the standard method for Wayland and works best on GNOME and other
+/// non-KDE environments.
+pub struct PortalBackend;
+
+impl CaptureBackend for PortalBackend {
+    fn name(&self) -> &'static str {
+        "portal"
+    }
+
+    fn is_available(&self) -> bool {
+        env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some()
+    }
+
+    fn capture(&self) -> LocalBoxFuture<'_, CaptureResult> {
+        Box::pin(async {
+            let image_path = capture_portal().await?;
+            read_and_remove(&image_path)
+        })
+    }
+}
+
+async fn capture_portal() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let connection = Connection::session().await?;
+    let proxy = portal::proxy(&connection, "org.freedesktop.portal.Screenshot").await?;
+
+    let request = portal::Request::new(&connection).await?;
+    let mut options: HashMap<&str, Value> = HashMap::new();
+    options.insert("handle_token", Str::from(request.token()).into());
+    options.insert("interactive", true.into());
+
+    // Request the screenshot and wait for the portal to respond with the URI of the saved file.
+    proxy.call_method("Screenshot", &("", options)).await?;
+    let results = request.response().await?;
+
+    // Extract the file path from the response URI.
+    let uri_value = results
+        .get("uri")
+        .ok_or("Portal response did not contain a URI.")?;
+
+    // Bind the Str to a variable so it lives long enough
+    let uri_str_obj = uri_value.downcast_ref::<Str>()?;
+    let uri_str = uri_str_obj.as_str();
+
+    let path_str = uri_str
+        .strip_prefix("file://")
+        .ok_or("URI was not a file URI.")?;
+    let decoded_path = urlencoding::decode(path_str)?.into_owned();
+
+    Ok(PathBuf::from(decoded_path))
+}
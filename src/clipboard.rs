@@ -0,0 +1,63 @@
+use arboard::{Clipboard, GetExtLinux, LinuxClipboardKind};
+use clap::ValueEnum;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+/// Where the text to look up comes from.
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// Select a screen region and run OCR on it.
+    Screen,
+    /// The clipboard (what was copied with Ctrl+C).
+    Clipboard,
+    /// The primary selection (whatever text is currently highlighted).
+    Primary,
+}
+
+/// What the clipboard or selection holds.
+pub enum ClipboardContent {
+    Text(String),
+    /// An image, encoded as PNG so it can go through the same OCR path as a screenshot.
+    Image(Vec<u8>),
+}
+
+/// Reads text from the clipboard or the primary selection, or an image when
+/// it holds no text (e.g. after "Copy Image" in a browser).
+pub fn read(source: Source) -> Result<ClipboardContent, Box<dyn std::error::Error>> {
+    let kind = match source {
+        Source::Screen => return Err("The screen is not a clipboard.".into()),
+        Source::Clipboard => LinuxClipboardKind::Clipboard,
+        Source::Primary => LinuxClipboardKind::Primary,
+    };
+    let mut clipboard = Clipboard::new()?;
+
+    match clipboard.get().clipboard(kind).text() {
+        Ok(text) if !text.trim().is_empty() => return Ok(ClipboardContent::Text(text)),
+        Ok(_) | Err(arboard::Error::ContentNotAvailable) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let image = match clipboard.get().clipboard(kind).image() {
+        Ok(image) => image,
+        Err(arboard::Error::ContentNotAvailable) => {
+            let name = match source {
+                Source::Primary => "primary selection",
+                _ => "clipboard",
+            };
+            return Err(format!("The {} holds no text or image.", name).into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let rgba = RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.into_owned(),
+    )
+    .ok_or("The clipboard image has an unexpected size.")?;
+
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(rgba).write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(ClipboardContent::Image(png))
+}
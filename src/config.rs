@@ -0,0 +1,395 @@
+use crate::capture::CaptureBackendKind;
+use crate::clipboard::Source;
+use crate::ocr::Psm;
+use crate::paths;
+use crate::preprocess::{self, PreprocessStep};
+use crate::translation::{self, ProviderKind};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// --- Data Structures ---
+
+/// Settings read from `config.toml` in the config directory. Every field is
+/// optional in the file; command-line flags override what it says.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Language(s) for OCR, e.g. `jpn` or `eng+tha`; `auto` uses every installed
+    /// language except the target.
+    pub ocr_lang: String,
+    /// Directory with more of Tesseract's `.traineddata` files, added to the bundled languages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tessdata_dir: Option<PathBuf>,
+    /// Language to translate into, e.g. `th`.
+    pub target: String,
+    /// Start with the window pinned.
+    pub pin: bool,
+    pub capture: CaptureConfig,
+    pub translation: TranslationConfig,
+    pub cache: CacheConfig,
+    pub history: HistoryConfig,
+    pub ui: UiConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    pub backend: CaptureBackendKind,
+    pub source: Source,
+    /// OCR words below this confidence (0-100) are dropped as noise. Without
+    /// it, the OCR language's default is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f32>,
+    pub preprocess: Vec<PreprocessStep>,
+    /// Page segmentation mode; `auto` picks one from the shape of the capture.
+    pub psm: Psm,
+    /// Scale factor of the screen, for `psm = "auto"`. Without it, `GDK_SCALE`
+    /// or `QT_SCALE_FACTOR` is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale_factor: Option<f32>,
+    /// Rejoin hyphenated words and wrapped lines and fix common OCR confusions
+    /// before translating.
+    pub normalize: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TranslationConfig {
+    pub providers: Vec<ProviderKind>,
+    /// Timeout for each network request, in seconds.
+    pub timeout_secs: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub libretranslate_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub libretranslate_api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stardict_dir: Option<PathBuf>,
+    /// Longdo dictionaries whose definitions are shown, in this order.
+    pub longdo_dictionaries: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub ttl_days: u64,
+    pub max_entries: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// The oldest lookups are dropped once the history holds more than this.
+    pub max_entries: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    pub theme: Theme,
+    /// Initial width of the result window, in points.
+    pub width: f32,
+    /// The result window grows with its content between these heights.
+    pub min_height: f32,
+    pub max_height: f32,
+    /// Size of the body text; the other text sizes scale with it.
+    pub font_size: f32,
+    /// Extra font files (.ttf/.otf), tried before the bundled Noto fonts.
+    pub fonts: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Dark,
+    Light,
+}
+
+// --- Defaults ---
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ocr_lang: "auto".to_string(),
+            tessdata_dir: None,
+            target: "th".to_string(),
+            pin: false,
+            capture: CaptureConfig::default(),
+            translation: TranslationConfig::default(),
+            cache: CacheConfig::default(),
+            history: HistoryConfig::default(),
+            ui: UiConfig::default(),
+        }
+    }
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            backend: CaptureBackendKind::Auto,
+            source: Source::Screen,
+            min_confidence: None,
+            preprocess: preprocess::DEFAULT_STEPS
+                .split(',')
+                .filter_map(|step| PreprocessStep::from_str(step, true).ok())
+                .collect(),
+            psm: Psm::Auto,
+            scale_factor: None,
+            normalize: true,
+        }
+    }
+}
+
+impl Default for TranslationConfig {
+    fn default() -> Self {
+        Self {
+            providers: vec![
+                ProviderKind::Google,
+                ProviderKind::Longdo,
+                ProviderKind::Stardict,
+            ],
+            timeout_secs: 10,
+            libretranslate_url: None,
+            libretranslate_api_key: None,
+            stardict_dir: None,
+            longdo_dictionaries: translation::DEFAULT_LONGDO_DICTIONARIES
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_days: 30,
+            max_entries: 5000,
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 10_000,
+        }
+    }
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            theme: Theme::Dark,
+            width: 500.0,
+            min_height: 160.0,
+            max_height: 720.0,
+            font_size: 16.0,
+            fonts: Vec::new(),
+        }
+    }
+}
+
+// --- Loading and Validation ---
+
+impl Config {
+    /// `config.toml` in the config directory, e.g. `~/.config/floating-dictionary-linux/config.toml`.
+    pub fn default_path() -> io::Result<PathBuf> {
+        Ok(paths::app_config_dir()?.join("config.toml"))
+    }
+
+    /// Reads the config file. A missing file gives the defaults.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+        };
+        toml::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// Hides the API key, so the printed settings can be shared safely.
+    pub fn redact_secrets(&mut self) {
+        if self.translation.libretranslate_api_key.is_some() {
+            self.translation.libretranslate_api_key = Some("***".to_string());
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string_pretty(self)
+    }
+
+    /// Checks the settings that parse but cannot work. Returns one message per problem.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(dir) = &self.tessdata_dir {
+            if !dir.is_dir() {
+                problems.push(format!("tessdata_dir {} is not a directory", dir.display()));
+            }
+        }
+        if self.target.trim().is_empty() {
+            problems.push("target must not be empty".to_string());
+        }
+        if self.ocr_lang.split('+').any(|name| name.trim().is_empty()) {
+            problems.push("ocr_lang must not be empty".to_string());
+        }
+        if let Some(min_confidence) = self.capture.min_confidence {
+            if !(0.0..=100.0).contains(&min_confidence) {
+                problems.push("capture.min_confidence must be between 0 and 100".to_string());
+            }
+        }
+        if let Some(scale_factor) = self.capture.scale_factor {
+            if !(0.5..=4.0).contains(&scale_factor) {
+                problems.push("capture.scale_factor must be between 0.5 and 4".to_string());
+            }
+        }
+        if self.translation.providers.is_empty() {
+            problems.push("translation.providers must list at least one provider".to_string());
+        }
+        if self
+            .translation
+            .providers
+            .contains(&ProviderKind::Libretranslate)
+            && self.translation.libretranslate_url.is_none()
+        {
+            problems.push(
+                "translation.libretranslate_url is required by the libretranslate provider"
+                    .to_string(),
+            );
+        }
+        if self.translation.timeout_secs == 0 {
+            problems.push("translation.timeout_secs must be at least 1".to_string());
+        }
+        if let Some(dir) = &self.translation.stardict_dir {
+            if !dir.is_dir() {
+                problems.push(format!(
+                    "translation.stardict_dir {} is not a directory",
+                    dir.display()
+                ));
+            }
+        }
+        if self.history.max_entries == 0 {
+            problems.push("history.max_entries must be at least 1".to_string());
+        }
+        if self.ui.width < 200.0 {
+            problems.push("ui.width must be at least 200".to_string());
+        }
+        if self.ui.min_height < 100.0 || self.ui.min_height > self.ui.max_height {
+            problems
+                .push("ui.min_height must be at least 100 and not above ui.max_height".to_string());
+        }
+        if !(8.0..=48.0).contains(&self.ui.font_size) {
+            problems.push("ui.font_size must be between 8 and 48".to_string());
+        }
+        for font in &self.ui.fonts {
+            if !font.is_file() {
+                problems.push(format!("ui.fonts: {} does not exist", font.display()));
+            }
+        }
+
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_toml() {
+        let mut config = Config {
+            ocr_lang: "jpn+eng".to_string(),
+            tessdata_dir: Some(PathBuf::from("/usr/share/tesseract/tessdata")),
+            ..Config::default()
+        };
+        config.capture.min_confidence = Some(55.0);
+        config.translation.providers = vec![ProviderKind::Stardict];
+        config.history.max_entries = 200;
+
+        let toml = config.to_toml().unwrap();
+        let parsed: Config = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.to_toml().unwrap(), toml);
+        assert_eq!(parsed.ocr_lang, "jpn+eng");
+        assert_eq!(parsed.capture.min_confidence, Some(55.0));
+        assert_eq!(parsed.history.max_entries, 200);
+    }
+
+    #[test]
+    fn missing_keys_take_the_defaults() {
+        let config: Config = toml::from_str("[cache]\nttl_days = 7\n").unwrap();
+        assert_eq!(config.cache.ttl_days, 7);
+        assert_eq!(config.cache.max_entries, CacheConfig::default().max_entries);
+        assert_eq!(config.target, Config::default().target);
+        assert!(config.history.enabled);
+    }
+
+    #[test]
+    fn load_rejects_unknown_keys_and_allows_a_missing_file() {
+        let dir = paths::test_dir("config-load");
+        assert!(Config::load(&dir.join("missing.toml")).is_ok());
+
+        let path = dir.join("config.toml");
+        fs::write(&path, "[history]\nenable = true\n").unwrap();
+        let error = Config::load(&path).unwrap_err().to_string();
+        assert!(error.contains("enable"), "{}", error);
+    }
+
+    #[test]
+    fn readme_example_parses() {
+        let readme = include_str!("../README.md");
+        let example = readme
+            .split("```toml\n")
+            .nth(1)
+            .and_then(|rest| rest.split("```").next())
+            .unwrap();
+        let config: Config = toml::from_str(example).unwrap();
+        assert!(config.validate().is_empty(), "{:?}", config.validate());
+    }
+
+    #[test]
+    fn validate_reports_each_problem() {
+        assert!(Config::default().validate().is_empty());
+
+        let mut config = Config {
+            target: " ".to_string(),
+            ocr_lang: "eng+".to_string(),
+            ..Config::default()
+        };
+        config.capture.min_confidence = Some(120.0);
+        config.translation.providers = vec![ProviderKind::Libretranslate];
+        config.history.max_entries = 0;
+        config.ui.min_height = 800.0;
+        let problems = config.validate();
+        for expected in [
+            "target",
+            "ocr_lang",
+            "capture.min_confidence",
+            "translation.libretranslate_url",
+            "history.max_entries",
+            "ui.min_height",
+        ] {
+            assert!(
+                problems.iter().any(|problem| problem.starts_with(expected)),
+                "{} missing from {:?}",
+                expected,
+                problems
+            );
+        }
+        assert_eq!(problems.len(), 6);
+    }
+
+    #[test]
+    fn redacts_the_api_key() {
+        let mut config = Config::default();
+        config.translation.libretranslate_api_key = Some("secret".to_string());
+        config.redact_secrets();
+        assert!(!config.to_toml().unwrap().contains("secret"));
+    }
+}
@@ -0,0 +1,257 @@
+use crate::app::{self, OcrApp};
+use crate::cache::Lookup;
+use crate::capture::{self, CaptureBackendKind};
+use crate::config::UiConfig;
+use crate::ocr::{OcrEngine, OcrResult, OcrSettings};
+use crate::portal::GlobalShortcut;
+use crate::preprocess::{self, PreprocessOptions};
+use crate::translation::{CombinedTranslationData, TranslateResult, TranslationError};
+use std::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::oneshot;
+use zbus::fdo;
+
+/// Well-known name, object path and interface of the session bus service.
+pub const BUS_NAME: &str = "io.github.gbb19.FloatingDictionary";
+pub const OBJECT_PATH: &str = "/io/github/gbb19/FloatingDictionary";
+pub const INTERFACE: &str = "io.github.gbb19.FloatingDictionary1";
+
+/// Everything the daemon needs to capture and recognize text, fixed at startup.
+pub struct DaemonOptions {
+    pub ocr: OcrSettings,
+    pub preprocess: PreprocessOptions,
+    pub capture_backend: CaptureBackendKind,
+    pub pin: bool,
+    /// Preferred trigger for the global capture shortcut, or `None` to not register one.
+    pub shortcut: Option<String>,
+    pub ui: UiConfig,
+}
+
+/// Windows to open. Only the main thread may run the UI event loop, so the
+/// D-Bus handlers hand windows over through a channel.
+enum UiRequest {
+    Result {
+        text: String,
+        ocr_result: Option<OcrResult>,
+        translation_rx: Receiver<TranslateResult<CombinedTranslationData>>,
+    },
+    History,
+}
+
+/// An image to recognize on the OCR thread, which owns the warm Tesseract instance.
+struct OcrJob {
+    image_data: Vec<u8>,
+    reply: oneshot::Sender<Result<OcrResult, String>>,
+}
+
+// --- Service ---
+
+#[derive(Clone)]
+struct DaemonService {
+    lookup: Lookup,
+    ocr_tx: Sender<OcrJob>,
+    ui_tx: Sender<UiRequest>,
+    capture_backend: CaptureBackendKind,
+}
+
+#[zbus::interface(name = "io.github.gbb19.FloatingDictionary1")]
+impl DaemonService {
+    /// Lets the user select a screen region, recognizes it and opens the
+    /// result window. Returns the recognized text.
+    async fn capture_and_translate(&self) -> fdo::Result<String> {
+        self.capture().await
+    }
+
+    /// Translates `text` and returns the result as JSON (see `CombinedTranslationData`).
+    /// With `show`, the result window is opened as well.
+    async fn translate_text(&self, text: String, show: bool) -> fdo::Result<String> {
+        let data = self
+            .translate(&text)
+            .await
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+
+        if show {
+            let (tx, translation_rx) = mpsc::channel();
+            let _ = tx.send(Ok(data.clone()));
+            self.show(UiRequest::Result {
+                text,
+                ocr_result: None,
+                translation_rx,
+            })?;
+        }
+        serde_json::to_string(&data).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Opens the window on the lookup history.
+    async fn show_history(&self) -> fdo::Result<()> {
+        self.show(UiRequest::History)
+    }
+}
+
+impl DaemonService {
+    /// Captures, recognizes and opens the result window; used by the D-Bus
+    /// method and by the global shortcut.
+    async fn capture(&self) -> fdo::Result<String> {
+        let image_data = capture_on_thread(self.capture_backend)
+            .await
+            .map_err(fdo::Error::Failed)?;
+
+        let (reply, result) = oneshot::channel();
+        self.ocr_tx
+            .send(OcrJob { image_data, reply })
+            .map_err(|_| fdo::Error::Failed("The OCR thread has stopped".to_string()))?;
+        let ocr_result = result
+            .await
+            .map_err(|_| fdo::Error::Failed("The OCR thread has stopped".to_string()))?
+            .map_err(fdo::Error::Failed)?;
+        if ocr_result.text.trim().is_empty() {
+            return Err(fdo::Error::Failed(
+                "No text was recognized in the selected region.".to_string(),
+            ));
+        }
+
+        let text = ocr_result.text.clone();
+        self.show(UiRequest::Result {
+            translation_rx: self.lookup.spawn(&text),
+            text: text.clone(),
+            ocr_result: Some(ocr_result),
+        })?;
+        Ok(text)
+    }
+
+    /// D-Bus handlers run on zbus's executor, which has no Tokio reactor for the
+    /// HTTP client, so the lookup runs on the program's runtime and is awaited here.
+    async fn translate(&self, text: &str) -> TranslateResult<CombinedTranslationData> {
+        let (reply, result) = oneshot::channel();
+        let lookup = self.lookup.clone();
+        let text = text.to_string();
+        self.lookup.runtime.spawn(async move {
+            let _ = reply.send(lookup.translate(&text).await);
+        });
+        result.await.unwrap_or_else(|_| {
+            Err(TranslationError::Network(
+                "The lookup task has stopped".to_string(),
+            ))
+        })
+    }
+
+    fn show(&self, request: UiRequest) -> fdo::Result<()> {
+        self.ui_tx
+            .send(request)
+            .map_err(|_| fdo::Error::Failed("The UI thread has stopped".to_string()))
+    }
+}
+
+// --- Daemon ---
+
+/// Registers the D-Bus service and then runs result windows on the calling
+/// (main) thread, one at a time, until the process is killed.
+pub async fn run(lookup: Lookup, options: DaemonOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let ocr_tx = spawn_ocr_thread(&options)?;
+    let (ui_tx, ui_rx) = mpsc::channel();
+
+    let service = DaemonService {
+        lookup: lookup.clone(),
+        ocr_tx,
+        ui_tx,
+        capture_backend: options.capture_backend,
+    };
+    let _connection = zbus::connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, service.clone())?
+        .build()
+        .await?;
+    println!("Listening on the session bus as {}.", BUS_NAME);
+
+    if let Some(trigger) = options.shortcut.clone() {
+        tokio::spawn(listen_for_shortcut(trigger, service));
+    }
+
+    for request in ui_rx {
+        let app = match request {
+            UiRequest::Result {
+                text,
+                ocr_result,
+                translation_rx,
+            } => OcrApp::new(
+                text,
+                ocr_result,
+                lookup.clone(),
+                translation_rx,
+                options.pin,
+            ),
+            UiRequest::History => OcrApp::with_history(lookup.clone(), options.pin),
+        };
+        if let Err(e) = app::run_window(app, &options.ui) {
+            eprintln!("Failed to open the window: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Registers the global shortcut and captures whenever it is pressed.
+async fn listen_for_shortcut(trigger: String, service: DaemonService) {
+    let mut shortcut = match GlobalShortcut::register(&trigger).await {
+        Ok(shortcut) => shortcut,
+        Err(e) => {
+            eprintln!(
+                "Could not register a global shortcut ({}). The GlobalShortcuts portal may be \
+                 unavailable on this desktop; bind `floating-dictionary-linux client capture` \
+                 to a key in the desktop settings instead.",
+                e
+            );
+            return;
+        }
+    };
+    println!("Press {} to capture and translate.", trigger);
+
+    while shortcut.activated().await {
+        if let Err(e) = service.capture().await {
+            eprintln!("Capture failed: {}", e);
+        }
+    }
+}
+
+/// Starts the thread that owns the Tesseract instance. The language data is
+/// loaded right away, so the first capture does not pay for it.
+fn spawn_ocr_thread(options: &DaemonOptions) -> Result<Sender<OcrJob>, Box<dyn std::error::Error>> {
+    let mut engine = OcrEngine::new();
+    engine.warm_up(&options.ocr)?;
+
+    let (tx, rx) = mpsc::channel::<OcrJob>();
+    let settings = options.ocr.clone();
+    let preprocess_options = options.preprocess.clone();
+    std::thread::spawn(move || {
+        for job in rx {
+            let result = preprocess::decode(&job.image_data)
+                .and_then(|image| {
+                    let capture_size = (image.width(), image.height());
+                    let image = preprocess::preprocess(image, &preprocess_options)?;
+                    engine.recognize(&image, &settings, capture_size)
+                })
+                .map_err(|e| e.to_string());
+            let _ = job.reply.send(result);
+        }
+    });
+    Ok(tx)
+}
+
+/// Capture backends are not `Send`, so each capture runs on its own thread.
+async fn capture_on_thread(kind: CaptureBackendKind) -> Result<Vec<u8>, String> {
+    let (reply, result) = oneshot::channel();
+    std::thread::spawn(move || {
+        let captured = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(rt) => rt
+                .block_on(capture::capture_image(kind))
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        let _ = reply.send(captured);
+    });
+    result
+        .await
+        .map_err(|_| "The capture thread has stopped".to_string())?
+}
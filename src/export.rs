@@ -0,0 +1,518 @@
+use crate::cache::fnv1a;
+use crate::history::HistoryEntry;
+use crate::translation::{ExampleItem, TranslationItem};
+use crate::vocab::VocabCard;
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::SimpleFileOptions;
+
+/// Only the first few example sentences go on a card, to keep it readable.
+const MAX_EXAMPLES: usize = 2;
+
+const FIELD_NAMES: [&str; 5] = [
+    "Word",
+    "Part of Speech",
+    "Translation",
+    "Definitions",
+    "Examples",
+];
+
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq)]
+pub enum ExportSource {
+    /// Words saved with the Save button.
+    Vocab,
+    /// Every past lookup (the newest result per word).
+    History,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq)]
+pub enum ExportFormat {
+    /// Tab-separated text for Anki's File > Import.
+    Tsv,
+    /// An Anki deck package.
+    Apkg,
+}
+
+impl ExportFormat {
+    /// Guesses the format from the file extension; anything but `.apkg` is TSV.
+    pub fn from_path(path: &Path) -> Self {
+        if path.extension().is_some_and(|ext| ext == "apkg") {
+            Self::Apkg
+        } else {
+            Self::Tsv
+        }
+    }
+}
+
+/// One flashcard. All fields are HTML.
+#[derive(Debug, Clone)]
+pub struct ExportNote {
+    pub word: String,
+    /// `word` as plain text, which Anki sorts and finds duplicates by.
+    pub plain_word: String,
+    pub pos: String,
+    pub translation: String,
+    pub definitions: String,
+    pub examples: String,
+}
+
+// --- Building Notes ---
+
+impl ExportNote {
+    pub fn from_card(card: &VocabCard) -> Self {
+        Self::new(
+            &card.word,
+            &card.translation,
+            &card.definitions,
+            &card.examples,
+        )
+    }
+
+    pub fn from_history(entry: &HistoryEntry) -> Self {
+        let data = &entry.data;
+        let mut definitions: Vec<TranslationItem> = data
+            .longdo_data
+            .iter()
+            .flat_map(|longdo| longdo.translations.iter().cloned())
+            .collect();
+        definitions.extend(
+            data.sections
+                .iter()
+                .flat_map(|section| section.entries.iter().cloned()),
+        );
+        let examples: Vec<ExampleItem> = data
+            .longdo_data
+            .iter()
+            .flat_map(|longdo| longdo.examples.iter().cloned())
+            .collect();
+
+        Self::new(&entry.text, data.summary(), &definitions, &examples)
+    }
+
+    fn new(
+        word: &str,
+        translation: &str,
+        definitions: &[TranslationItem],
+        examples: &[ExampleItem],
+    ) -> Self {
+        let mut parts_of_speech: Vec<&str> = Vec::new();
+        for item in definitions {
+            let pos = item.pos.trim();
+            if !pos.is_empty() && pos != "N/A" && !parts_of_speech.contains(&pos) {
+                parts_of_speech.push(pos);
+            }
+        }
+
+        Self {
+            word: escape_html(word.trim()),
+            plain_word: word.trim().to_string(),
+            pos: escape_html(&parts_of_speech.join(", ")),
+            translation: escape_html(translation),
+            definitions: definitions
+                .iter()
+                .map(|item| {
+                    format!(
+                        "[{}] {} ({})",
+                        escape_html(&item.pos),
+                        escape_html(&item.translation),
+                        escape_html(&item.dictionary)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("<br>"),
+            examples: examples
+                .iter()
+                .take(MAX_EXAMPLES)
+                .map(|ex| format!("{}<br>→ {}", escape_html(&ex.en), escape_html(&ex.th)))
+                .collect::<Vec<_>>()
+                .join("<br><br>"),
+        }
+    }
+
+    fn fields(&self) -> [&str; 5] {
+        [
+            &self.word,
+            &self.pos,
+            &self.translation,
+            &self.definitions,
+            &self.examples,
+        ]
+    }
+}
+
+/// Turns the history into notes, keeping only the newest lookup of each word.
+pub fn notes_from_history(entries: &[HistoryEntry]) -> Vec<ExportNote> {
+    let mut seen = HashSet::new();
+    entries
+        .iter()
+        .filter(|entry| seen.insert(entry.text.to_lowercase()))
+        .map(ExportNote::from_history)
+        .collect()
+}
+
+// --- TSV ---
+
+/// Writes the notes as tab-separated text, with the header lines Anki (2.1.55+) understands.
+pub fn write_tsv(notes: &[ExportNote], path: &Path) -> std::io::Result<()> {
+    let mut out = String::new();
+    out.push_str("#separator:tab\n#html:true\n");
+    out.push_str(&format!("#columns:{}\n", FIELD_NAMES.join("\t")));
+    for note in notes {
+        let fields: Vec<String> = note
+            .fields()
+            .iter()
+            .map(|field| field.replace(['\t', '\n', '\r'], " "))
+            .collect();
+        out.push_str(&fields.join("\t"));
+        out.push('\n');
+    }
+    fs::write(path, out)
+}
+
+// --- APKG ---
+
+/// Writes the notes as an Anki package: a zip holding a `collection.anki2`
+/// SQLite database (schema version 11) and an empty media map.
+///
+/// Note IDs (GUIDs) are derived from the word, so importing a newer export
+/// updates the existing notes instead of duplicating them.
+pub fn write_apkg(
+    notes: &[ExportNote],
+    deck_name: &str,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db_path = std::env::temp_dir().join(format!(
+        "floating-dictionary-export-{}.anki2",
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&db_path);
+
+    let result = build_collection(notes, deck_name, &db_path).and_then(|()| {
+        let mut zip = zip::ZipWriter::new(File::create(path)?);
+        let options = SimpleFileOptions::default();
+        zip.start_file("collection.anki2", options)?;
+        zip.write_all(&fs::read(&db_path)?)?;
+        zip.start_file("media", options)?;
+        zip.write_all(b"{}")?;
+        zip.finish()?;
+        Ok(())
+    });
+
+    let _ = fs::remove_file(&db_path);
+    result
+}
+
+fn build_collection(
+    notes: &[ExportNote],
+    deck_name: &str,
+    db_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+    let now = now_ms / 1000;
+    // Stable IDs, so repeated exports map onto the same deck and note type in Anki.
+    let deck_id = (fnv1a(deck_name.as_bytes()) >> 13) as i64;
+    let model_id = (fnv1a(b"floating-dictionary-model") >> 13) as i64;
+
+    let conn = Connection::open(db_path)?;
+    conn.execute_batch(APKG_SCHEMA)?;
+
+    let conf = json!({
+        "nextPos": notes.len() + 1,
+        "estTimes": true,
+        "activeDecks": [deck_id],
+        "sortType": "noteFld",
+        "timeLim": 0,
+        "sortBackwards": false,
+        "addToCur": true,
+        "curDeck": deck_id,
+        "newBury": true,
+        "newSpread": 0,
+        "dueCounts": true,
+        "curModel": model_id.to_string(),
+        "collapseTime": 1200,
+    });
+    let mut decks = serde_json::Map::new();
+    decks.insert("1".to_string(), deck_json(1, "Default", now));
+    decks.insert(deck_id.to_string(), deck_json(deck_id, deck_name, now));
+    let models = json!({ model_id.to_string(): model_json(model_id, deck_id, now) });
+    let dconf = json!({ "1": deck_config_json(now) });
+
+    conn.execute(
+        "INSERT INTO col VALUES (1, ?1, ?2, ?2, 11, 0, 0, 0, ?3, ?4, ?5, ?6, '{}')",
+        params![
+            now,
+            now_ms,
+            conf.to_string(),
+            models.to_string(),
+            serde_json::Value::Object(decks).to_string(),
+            dconf.to_string()
+        ],
+    )?;
+
+    for (position, note) in notes.iter().enumerate() {
+        let note_id = now_ms + position as i64;
+        conn.execute(
+            "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, '', ?5, ?6, ?7, 0, '')",
+            params![
+                note_id,
+                note_guid(note),
+                model_id,
+                now,
+                note.fields().join("\x1f"),
+                note.plain_word,
+                field_checksum(&note.plain_word)
+            ],
+        )?;
+        conn.execute(
+            "INSERT INTO cards VALUES (?1, ?2, ?3, 0, ?4, -1, 0, 0, ?5, 0, 0, 0, 0, 0, 0, 0, 0, '')",
+            params![note_id, note_id, deck_id, now, position as i64 + 1],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn deck_json(id: i64, name: &str, now: i64) -> serde_json::Value {
+    json!({
+        "id": id,
+        "name": name,
+        "desc": "",
+        "mod": now,
+        "usn": -1,
+        "collapsed": false,
+        "newToday": [0, 0],
+        "revToday": [0, 0],
+        "lrnToday": [0, 0],
+        "timeToday": [0, 0],
+        "extendNew": 10,
+        "extendRev": 50,
+        "dyn": 0,
+        "conf": 1,
+    })
+}
+
+fn model_json(id: i64, deck_id: i64, now: i64) -> serde_json::Value {
+    let fields: Vec<serde_json::Value> = FIELD_NAMES
+        .iter()
+        .enumerate()
+        .map(|(ord, name)| {
+            json!({
+                "name": name,
+                "ord": ord,
+                "sticky": false,
+                "rtl": false,
+                "font": "Arial",
+                "size": 20,
+                "media": [],
+            })
+        })
+        .collect();
+
+    json!({
+        "id": id,
+        "name": "Floating Dictionary",
+        "type": 0,
+        "mod": now,
+        "usn": -1,
+        "sortf": 0,
+        "did": deck_id,
+        "tags": [],
+        "vers": [],
+        "flds": fields,
+        "tmpls": [{
+            "name": "Word",
+            "ord": 0,
+            "qfmt": "<div class=word>{{Word}}</div>",
+            "afmt": "{{FrontSide}}<hr id=answer>\
+                     <div class=pos>{{Part of Speech}}</div>\
+                     <div>{{Translation}}</div><br>\
+                     <div class=defs>{{Definitions}}</div><br>\
+                     <div class=examples>{{Examples}}</div>",
+            "bqfmt": "",
+            "bafmt": "",
+            "did": null,
+        }],
+        "css": ".card { font-family: sans-serif; font-size: 20px; text-align: center; }\n\
+                .word { font-size: 32px; font-weight: bold; }\n\
+                .pos { font-style: italic; color: #888; }\n\
+                .defs, .examples { font-size: 16px; text-align: left; }",
+        "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+        "latexPost": "\\end{document}",
+        "req": [[0, "any", [0]]],
+    })
+}
+
+/// Anki's default deck options.
+fn deck_config_json(now: i64) -> serde_json::Value {
+    json!({
+        "id": 1,
+        "name": "Default",
+        "mod": now,
+        "usn": 0,
+        "maxTaken": 60,
+        "autoplay": true,
+        "timer": 0,
+        "replayq": true,
+        "dyn": false,
+        "new": {
+            "bury": true,
+            "delays": [1, 10],
+            "initialFactor": 2500,
+            "ints": [1, 4, 7],
+            "order": 1,
+            "perDay": 20,
+            "separate": true,
+        },
+        "lapse": {
+            "delays": [10],
+            "leechAction": 0,
+            "leechFails": 8,
+            "minInt": 1,
+            "mult": 0,
+        },
+        "rev": {
+            "bury": true,
+            "ease4": 1.3,
+            "fuzz": 0.05,
+            "ivlFct": 1,
+            "maxIvl": 36500,
+            "minSpace": 1,
+            "perDay": 100,
+        },
+    })
+}
+
+const APKG_SCHEMA: &str = "
+CREATE TABLE col (
+    id integer primary key, crt integer not null, mod integer not null,
+    scm integer not null, ver integer not null, dty integer not null,
+    usn integer not null, ls integer not null, conf text not null,
+    models text not null, decks text not null, dconf text not null, tags text not null
+);
+CREATE TABLE notes (
+    id integer primary key, guid text not null, mid integer not null,
+    mod integer not null, usn integer not null, tags text not null,
+    flds text not null, sfld integer not null, csum integer not null,
+    flags integer not null, data text not null
+);
+CREATE TABLE cards (
+    id integer primary key, nid integer not null, did integer not null,
+    ord integer not null, mod integer not null, usn integer not null,
+    type integer not null, queue integer not null, due integer not null,
+    ivl integer not null, factor integer not null, reps integer not null,
+    lapses integer not null, left integer not null, odue integer not null,
+    odid integer not null, flags integer not null, data text not null
+);
+CREATE TABLE revlog (
+    id integer primary key, cid integer not null, usn integer not null,
+    ease integer not null, ivl integer not null, lastIvl integer not null,
+    factor integer not null, time integer not null, type integer not null
+);
+CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null);
+CREATE INDEX ix_notes_usn ON notes (usn);
+CREATE INDEX ix_cards_usn ON cards (usn);
+CREATE INDEX ix_revlog_usn ON revlog (usn);
+CREATE INDEX ix_cards_nid ON cards (nid);
+CREATE INDEX ix_cards_sched ON cards (did, queue, due);
+CREATE INDEX ix_revlog_cid ON revlog (cid);
+CREATE INDEX ix_notes_csum ON notes (csum);
+";
+
+// --- Helper Functions ---
+
+/// Derived from the plain word, so a word keeps its note however it is
+/// capitalized and whether or not it needed HTML escaping.
+fn note_guid(note: &ExportNote) -> String {
+    format!(
+        "fd{:016x}",
+        fnv1a(note.plain_word.to_lowercase().as_bytes())
+    )
+}
+
+/// Anki's duplicate check: the first 8 hex digits of the SHA-1 of the sort field.
+fn field_checksum(field: &str) -> i64 {
+    let digest = Sha1::digest(field.as_bytes());
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) as i64
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+
+    fn note(word: &str, translation: &str) -> ExportNote {
+        ExportNote::new(word, translation, &[], &[])
+    }
+
+    #[test]
+    fn tsv_escapes_html_and_separators() {
+        let path = paths::test_dir("export-tsv").join("words.txt");
+        write_tsv(&[note("R&D", "line one\nline\ttwo <b>")], &path).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[..2], ["#separator:tab", "#html:true"]);
+        assert!(lines[2].starts_with("#columns:Word\tPart of Speech"));
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[3].split('\t').collect::<Vec<_>>(),
+            ["R&amp;D", "", "line one line two &lt;b&gt;", "", ""]
+        );
+    }
+
+    #[test]
+    fn guid_follows_the_plain_word() {
+        let guid = note_guid(&note("R&D", ""));
+        assert_eq!(guid, note_guid(&note(" r&d ", "other")));
+        assert_eq!(
+            guid,
+            format!("fd{:016x}", fnv1a(b"r&d")),
+            "hashed before HTML escaping"
+        );
+        assert_ne!(guid, note_guid(&note("R&amp;D", "")));
+    }
+
+    #[test]
+    fn checksum_is_the_start_of_the_sha1() {
+        // SHA-1("hello") = aaf4c61d...
+        assert_eq!(field_checksum("hello"), 0xaaf4c61d);
+    }
+
+    #[test]
+    fn apkg_collection_holds_the_notes() {
+        let db_path = paths::test_dir("export-apkg").join("collection.anki2");
+        let notes = [note("hello", "สวัสดี"), note("R&D", "research")];
+        build_collection(&notes, "Test Deck", &db_path).unwrap();
+
+        let conn = Connection::open(&db_path).unwrap();
+        let rows: Vec<(String, String, i64)> = conn
+            .prepare("SELECT guid, sfld, csum FROM notes ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].0, note_guid(&notes[1]));
+        assert_eq!(rows[1].1, "R&D");
+        assert_eq!(rows[1].2, field_checksum("R&D"));
+
+        let cards: i64 = conn
+            .query_row("SELECT COUNT(*) FROM cards", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cards, 2);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::paths;
+use crate::translation::CombinedTranslationData;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A history over `max_entries` is cut down to this share of it, so the file
+/// only needs to be rewritten once every many lookups.
+const COMPACT_TO_PERCENT: usize = 90;
+
+// --- Data Structures ---
+
+/// Every lookup, appended as one JSON line to `history.jsonl` in the data directory.
+/// The oldest lines are dropped once there are more than `max_entries`.
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+    max_entries: usize,
+    /// Lookups that can still be recorded before the lines are counted again.
+    /// Shared by the clones; other processes recording at the same time only
+    /// make the limit a little soft.
+    room: Arc<AtomicUsize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: u64, // Seconds since the Unix epoch
+    /// The looked-up text, as recognized by OCR or typed by the user.
+    pub text: String,
+    /// The detected (or requested) source language, e.g. `EN`.
+    pub source_lang: String,
+    pub target_lang: String,
+    pub data: CombinedTranslationData,
+}
+
+/// Narrows the history down by text and language. Empty fields match everything.
+#[derive(Debug, Default, Clone)]
+pub struct HistoryFilter {
+    pub search: String,
+    pub lang: String,
+}
+
+// --- Store Implementation ---
+
+impl History {
+    pub fn open(max_entries: usize) -> io::Result<Self> {
+        let dir = paths::app_data_dir()?;
+        fs::create_dir_all(&dir)?;
+        Ok(Self::at(dir.join("history.jsonl"), max_entries))
+    }
+
+    fn at(path: PathBuf, max_entries: usize) -> Self {
+        Self {
+            path,
+            max_entries,
+            room: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a finished lookup to the history and drops the oldest ones if
+    /// it is over its size limit.
+    pub fn record(&self, text: &str, data: &CombinedTranslationData) -> io::Result<()> {
+        let entry = HistoryEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            text: text.trim().to_string(),
+            source_lang: data.source_lang.clone(),
+            target_lang: data.target_lang.clone(),
+            data: data.clone(),
+        };
+
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+        self.compact()
+    }
+
+    /// Loads all entries, newest first. Lines that fail to parse are skipped.
+    pub fn load(&self) -> io::Result<Vec<HistoryEntry>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut entries: Vec<HistoryEntry> = contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Called after every record. Like the cache's eviction, the file is only
+    /// read once the room left from the last count is used up, and only
+    /// rewritten when it holds too many lines.
+    fn compact(&self) -> io::Result<()> {
+        let room = self.room.load(Ordering::Relaxed);
+        if room > 0 {
+            self.room.store(room - 1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let contents = fs::read_to_string(&self.path)?;
+        let count = contents.lines().count();
+        if count <= self.max_entries {
+            self.room.store(self.max_entries - count, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let keep = self.max_entries * COMPACT_TO_PERCENT / 100;
+        let mut kept = String::new();
+        for line in contents.lines().skip(count - keep) {
+            kept.push_str(line);
+            kept.push('\n');
+        }
+        // Written next to the history and renamed over it, so a crash never
+        // leaves it half written.
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp_path, kept)?;
+        fs::rename(&tmp_path, &self.path)?;
+        self.room.store(self.max_entries - keep, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Removes every entry and returns how many were removed.
+    pub fn clear(&self) -> io::Result<usize> {
+        let count = self.load()?.len();
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(count),
+        }
+    }
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        let search = self.search.trim().to_lowercase();
+        let lang = self.lang.trim();
+
+        let text_matches = search.is_empty()
+            || entry.text.to_lowercase().contains(&search)
+            || entry.data.summary().to_lowercase().contains(&search);
+        let lang_matches = lang.is_empty()
+            || entry.source_lang.eq_ignore_ascii_case(lang)
+            || entry.target_lang.eq_ignore_ascii_case(lang);
+
+        text_matches && lang_matches
+    }
+}
+
+// --- Helper Functions ---
+
+/// Formats a Unix timestamp as `YYYY-MM-DD HH:MM` (UTC).
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds_of_day = timestamp % 86_400;
+
+    // Civil-from-days conversion (proleptic Gregorian calendar).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds_of_day / 3_600,
+        seconds_of_day % 3_600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, translation: &str, source_lang: &str) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: 0,
+            text: text.to_string(),
+            source_lang: source_lang.to_string(),
+            target_lang: "th".to_string(),
+            data: CombinedTranslationData::sample(text, translation),
+        }
+    }
+
+    fn filter(search: &str, lang: &str) -> HistoryFilter {
+        HistoryFilter {
+            search: search.to_string(),
+            lang: lang.to_string(),
+        }
+    }
+
+    #[test]
+    fn filter_matches_text_translation_and_language() {
+        let hello = entry("Hello", "สวัสดี", "EN");
+        assert!(filter("", "").matches(&hello));
+        assert!(filter(" hELLo ", "").matches(&hello));
+        assert!(filter("สวัส", "").matches(&hello));
+        assert!(filter("", "en").matches(&hello));
+        assert!(filter("", "TH").matches(&hello));
+        assert!(filter("hello", "en").matches(&hello));
+
+        assert!(!filter("goodbye", "").matches(&hello));
+        assert!(!filter("", "ja").matches(&hello));
+        assert!(!filter("hello", "ja").matches(&hello));
+    }
+
+    #[test]
+    fn formats_timestamps_in_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13");
+        assert_eq!(format_timestamp(4_107_542_399), "2100-02-28 23:59");
+    }
+
+    #[test]
+    fn loads_newest_first_and_skips_broken_lines() {
+        let history = History::at(paths::test_dir("history-load").join("history.jsonl"), 100);
+        assert!(history.load().unwrap().is_empty());
+
+        history
+            .record("first", &CombinedTranslationData::sample("first", "หนึ่ง"))
+            .unwrap();
+        OpenOptions::new()
+            .append(true)
+            .open(history.path())
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+        history
+            .record(
+                " second ",
+                &CombinedTranslationData::sample("second", "สอง"),
+            )
+            .unwrap();
+
+        let texts: Vec<String> = history
+            .load()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.text)
+            .collect();
+        assert_eq!(texts, ["second", "first"]);
+        assert_eq!(history.clear().unwrap(), 2);
+        assert!(history.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn drops_the_oldest_beyond_max_entries() {
+        let history = History::at(paths::test_dir("history-compact").join("history.jsonl"), 10);
+        let data = CombinedTranslationData::sample("word", "คำ");
+        for i in 0..10 {
+            history.record(&format!("word {}", i), &data).unwrap();
+        }
+        assert_eq!(history.load().unwrap().len(), 10);
+
+        history.record("word 10", &data).unwrap();
+        let entries = history.load().unwrap();
+        assert_eq!(entries.len(), 9);
+        assert_eq!(entries[0].text, "word 10");
+        assert_eq!(entries[8].text, "word 2");
+    }
+}
@@ -0,0 +1,409 @@
+use crate::paths;
+use crate::script::Script;
+use crate::tessdata;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Minimum OCR word confidence when several languages are loaded, or the
+/// language does not set its own.
+pub const DEFAULT_MIN_CONFIDENCE: f32 = 40.0;
+
+// --- Data Structures ---
+
+/// The font for a script: compiled into the binary, or for the large CJK
+/// fonts, read from the installed font files (see `fonts`).
+#[derive(Debug)]
+pub struct BundledFont {
+    /// Key of the font in egui's font definitions.
+    pub name: &'static str,
+    pub source: FontSource,
+}
+
+#[derive(Debug)]
+pub enum FontSource {
+    Embedded(&'static [u8]),
+    /// File names that hold the font, with the face index in each, in order
+    /// of preference; the first one found in `font_dirs()` is used.
+    Installed(&'static [(&'static str, u32)]),
+}
+
+/// A font ready to be handed to egui.
+#[derive(Debug)]
+pub struct LoadedFont {
+    pub name: &'static str,
+    pub data: &'static [u8],
+    pub index: u32,
+}
+
+/// A language Tesseract can read, with what the rest of the program needs to know about it.
+#[derive(Debug, Clone)]
+pub struct Language {
+    /// Name of the Tesseract data file, e.g. `chi_sim` for `chi_sim.traineddata`.
+    pub tesseract: Cow<'static, str>,
+    /// Code the translation providers use (ISO 639-1, or Google's `zh-CN`), if known.
+    pub code: Option<&'static str>,
+    /// Other names `--ocr-lang` accepts.
+    pub aliases: &'static [&'static str],
+    pub name: Cow<'static, str>,
+    pub script: Script,
+    /// Bundled font that covers the script, if there is one.
+    pub font: Option<&'static BundledFont>,
+    /// OCR words below this confidence are dropped, unless `min_confidence` is set.
+    /// Tesseract reports lower confidences for complex scripts.
+    pub min_confidence: f32,
+    /// Tessdata file for vertical text, e.g. `jpn_vert`. In a registry, only set
+    /// when the file is installed.
+    pub vertical: Option<&'static str>,
+}
+
+// --- Known Languages ---
+
+static NOTO_SANS: BundledFont = BundledFont {
+    name: "noto_sans",
+    source: FontSource::Embedded(include_bytes!("../assets/fonts/NotoSans-Regular.ttf")),
+};
+static NOTO_SANS_THAI: BundledFont = BundledFont {
+    name: "noto_sans_thai",
+    source: FontSource::Embedded(include_bytes!("../assets/fonts/NotoSansThai-Regular.ttf")),
+};
+// The CJK fonts are too large to compile in. Each is looked for on its own
+// (as downloaded from Google Fonts), then in the Noto CJK collection most
+// distributions package, whose faces are Japanese, Korean, Simplified and
+// Traditional Chinese in that order.
+static NOTO_SANS_JP: BundledFont = BundledFont {
+    name: "noto_sans_jp",
+    source: FontSource::Installed(&[
+        ("NotoSansJP-Regular.ttf", 0),
+        ("NotoSansCJKjp-Regular.otf", 0),
+        ("NotoSansCJK-Regular.ttc", 0),
+    ]),
+};
+static NOTO_SANS_KR: BundledFont = BundledFont {
+    name: "noto_sans_kr",
+    source: FontSource::Installed(&[
+        ("NotoSansKR-Regular.ttf", 0),
+        ("NotoSansCJKkr-Regular.otf", 0),
+        ("NotoSansCJK-Regular.ttc", 1),
+    ]),
+};
+static NOTO_SANS_SC: BundledFont = BundledFont {
+    name: "noto_sans_sc",
+    source: FontSource::Installed(&[
+        ("NotoSansSC-Regular.ttf", 0),
+        ("NotoSansCJKsc-Regular.otf", 0),
+        ("NotoSansCJK-Regular.ttc", 2),
+    ]),
+};
+static NOTO_SANS_TC: BundledFont = BundledFont {
+    name: "noto_sans_tc",
+    source: FontSource::Installed(&[
+        ("NotoSansTC-Regular.ttf", 0),
+        ("NotoSansCJKtc-Regular.otf", 0),
+        ("NotoSansCJK-Regular.ttc", 3),
+    ]),
+};
+
+/// Every language the program knows by name. The first six have bundled OCR data; the
+/// others are recognized once their `.traineddata` is installed with `langs install`.
+/// A new language only needs an entry here. The order is the font fallback order.
+#[rustfmt::skip]
+static KNOWN: &[Language] = &[
+    //     Tesseract  Code     Aliases    Name                     Script             Font             Min. conf.  Vertical data
+    known("eng",     "en",    &[],       "English",               Script::Latin,     &NOTO_SANS,      40.0,       None),
+    known("rus",     "ru",    &[],       "Russian",               Script::Cyrillic,  &NOTO_SANS,      40.0,       None),
+    known("tha",     "th",    &["thai"], "Thai",                  Script::Thai,      &NOTO_SANS_THAI, 30.0,       None),
+    known("jpn",     "ja",    &[],       "Japanese",              Script::Japanese,  &NOTO_SANS_JP,   30.0,       Some("jpn_vert")),
+    known("kor",     "ko",    &[],       "Korean",                Script::Hangul,    &NOTO_SANS_KR,   30.0,       Some("kor_vert")),
+    known("chi_sim", "zh-CN", &[],       "Chinese (Simplified)",  Script::Han,       &NOTO_SANS_SC,   30.0,       Some("chi_sim_vert")),
+    known("chi_tra", "zh-TW", &[],       "Chinese (Traditional)", Script::Han,       &NOTO_SANS_TC,   30.0,       Some("chi_tra_vert")),
+    known("deu",     "de",    &[],       "German",                Script::Latin,     &NOTO_SANS,      40.0,       None),
+    known("fra",     "fr",    &[],       "French",                Script::Latin,     &NOTO_SANS,      40.0,       None),
+    known("spa",     "es",    &[],       "Spanish",               Script::Latin,     &NOTO_SANS,      40.0,       None),
+    known("vie",     "vi",    &[],       "Vietnamese",            Script::Latin,     &NOTO_SANS,      40.0,       None),
+];
+
+// One argument per column of the table above.
+#[allow(clippy::too_many_arguments)]
+const fn known(
+    tesseract: &'static str,
+    code: &'static str,
+    aliases: &'static [&'static str],
+    name: &'static str,
+    script: Script,
+    font: &'static BundledFont,
+    min_confidence: f32,
+    vertical: Option<&'static str>,
+) -> Language {
+    Language {
+        tesseract: Cow::Borrowed(tesseract),
+        code: Some(code),
+        aliases,
+        name: Cow::Borrowed(name),
+        script,
+        font: Some(font),
+        min_confidence,
+        vertical,
+    }
+}
+
+/// The fonts of the known languages, without duplicates, in fallback order.
+/// A CJK font that is not installed is left out; its script then shows as
+/// boxes. The files are read once and kept for the life of the process, as
+/// every window needs them.
+pub fn fonts() -> &'static [LoadedFont] {
+    static FONTS: OnceLock<Vec<LoadedFont>> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let installed = find_font_files(&font_dirs());
+        let mut files: HashMap<&Path, &'static [u8]> = HashMap::new();
+        let mut fonts: Vec<LoadedFont> = Vec::new();
+        for font in KNOWN.iter().filter_map(|language| language.font) {
+            if fonts.iter().any(|f| f.name == font.name) {
+                continue;
+            }
+            let (data, index) = match &font.source {
+                FontSource::Embedded(data) => (*data, 0),
+                FontSource::Installed(candidates) => {
+                    let found = candidates.iter().find_map(|(file, index)| {
+                        let path = installed.get(*file)?;
+                        let data = match files.get(path.as_path()) {
+                            Some(data) => *data,
+                            None => {
+                                let data: &'static [u8] = fs::read(path).ok()?.leak();
+                                files.insert(path, data);
+                                data
+                            }
+                        };
+                        Some((data, *index))
+                    });
+                    let Some(found) = found else {
+                        continue;
+                    };
+                    found
+                }
+            };
+            fonts.push(LoadedFont {
+                name: font.name,
+                data,
+                index,
+            });
+        }
+        fonts
+    })
+}
+
+/// Where installed fonts are looked for, searched recursively: `fonts` in the
+/// data directory first, then the system font directories.
+pub fn font_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Ok(dir) = paths::app_data_dir() {
+        dirs.push(dir.join("fonts"));
+    }
+    if let Some(dir) = dirs::data_dir() {
+        dirs.push(dir.join("fonts"));
+    }
+    dirs.push(PathBuf::from("/usr/local/share/fonts"));
+    dirs.push(PathBuf::from("/usr/share/fonts"));
+    dirs
+}
+
+/// Maps font file names to the first path each is found at.
+fn find_font_files(dirs: &[PathBuf]) -> HashMap<String, PathBuf> {
+    fn visit(dir: &Path, depth: usize, found: &mut HashMap<String, PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if depth < 4 {
+                    visit(&path, depth + 1, found);
+                }
+            } else if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                found.entry(name.to_string()).or_insert(path);
+            }
+        }
+    }
+
+    let mut found = HashMap::new();
+    for dir in dirs {
+        visit(dir, 0, &mut found);
+    }
+    found
+}
+
+// --- Registry ---
+
+/// The languages that can be used for OCR: the known languages whose data is
+/// installed, followed by any other `.traineddata` in the tessdata directory.
+/// Vertical data files (`jpn_vert`) belong to their language instead of being
+/// languages of their own.
+pub struct Registry {
+    languages: Vec<Language>,
+}
+
+impl Registry {
+    pub fn load(tessdata_dir: &Path) -> io::Result<Self> {
+        let installed = tessdata::languages(tessdata_dir)?;
+        let is_installed = |code: &str| installed.iter().any(|installed| installed == code);
+        let mut languages: Vec<Language> = KNOWN
+            .iter()
+            .filter(|language| is_installed(&language.tesseract))
+            .cloned()
+            .map(|mut language| {
+                language.vertical = language.vertical.filter(|code| is_installed(code));
+                language
+            })
+            .collect();
+        for code in &installed {
+            let known = KNOWN.iter().any(|language| {
+                language.tesseract == code.as_str() || language.vertical == Some(code.as_str())
+            });
+            if !known {
+                languages.push(Language::unknown(code.clone()));
+            }
+        }
+        Ok(Self { languages })
+    }
+
+    /// Finds an installed language by its Tesseract name, translation code or alias.
+    /// The name of its vertical data (`jpn_vert`) finds the language as well.
+    pub fn find(&self, name: &str) -> Option<&Language> {
+        self.languages.iter().find(|language| {
+            language.tesseract == name
+                || language.vertical == Some(name)
+                || language
+                    .code
+                    .is_some_and(|code| code.eq_ignore_ascii_case(name))
+                || language.aliases.contains(&name)
+        })
+    }
+
+    /// Turns an `--ocr-lang` value into the languages to load. `auto` is every
+    /// installed language except the target language; anything else is one or
+    /// more names joined with `+`, e.g. `eng+tha`.
+    pub fn resolve(&self, ocr_lang: &str, target: &str) -> Result<Vec<&Language>, String> {
+        if ocr_lang == "auto" {
+            let languages: Vec<&Language> = self
+                .languages
+                .iter()
+                .filter(|language| !language.is_target(target))
+                .collect();
+            if languages.is_empty() {
+                return Err("No OCR languages are installed besides the target language.".into());
+            }
+            return Ok(languages);
+        }
+
+        ocr_lang
+            .split('+')
+            .map(|name| {
+                self.find(name.trim()).ok_or_else(|| {
+                    format!(
+                        "OCR language '{}' is not installed. `langs list` shows the installed languages.",
+                        name
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+impl Language {
+    /// A `.traineddata` file the registry has no entry for. It can still be
+    /// selected by its file name.
+    fn unknown(tesseract: String) -> Self {
+        Self {
+            name: Cow::Owned(tesseract.clone()),
+            tesseract: Cow::Owned(tesseract),
+            code: None,
+            aliases: &[],
+            script: Script::Unknown,
+            font: None,
+            min_confidence: DEFAULT_MIN_CONFIDENCE,
+            vertical: None,
+        }
+    }
+
+    /// Whether this is the language being translated into. A target without a
+    /// region (`zh`) matches any region; one with a region (`zh-TW`) only itself.
+    fn is_target(&self, target: &str) -> bool {
+        let Some(code) = self.code else {
+            return false;
+        };
+        code.eq_ignore_ascii_case(target)
+            || (!target.contains('-')
+                && code
+                    .split('-')
+                    .next()
+                    .is_some_and(|bare| bare.eq_ignore_ascii_case(target)))
+    }
+
+    /// The tessdata file to load. For vertical text it is the vertical data,
+    /// where that is installed.
+    pub fn data_name(&self, vertical: bool) -> &str {
+        match self.vertical {
+            Some(code) if vertical => code,
+            _ => &self.tesseract,
+        }
+    }
+}
+
+/// The language string Tesseract expects, e.g. `eng+tha`.
+pub fn tesseract_string(languages: &[&Language], vertical: bool) -> String {
+    languages
+        .iter()
+        .map(|language| language.data_name(vertical))
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// The minimum word confidence for these languages: the language's own when
+/// there is only one, and the general default otherwise.
+pub fn min_confidence(languages: &[&Language]) -> f32 {
+    match languages {
+        [language] => language.min_confidence,
+        _ => DEFAULT_MIN_CONFIDENCE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn font_files_are_found_in_subdirectories_in_order() {
+        let user = paths::test_dir("fonts-user");
+        let system = paths::test_dir("fonts-system");
+        fs::create_dir_all(system.join("opentype/noto")).unwrap();
+        fs::write(system.join("opentype/noto/NotoSansCJK-Regular.ttc"), "").unwrap();
+        fs::write(system.join("NotoSansJP-Regular.ttf"), "").unwrap();
+        fs::write(user.join("NotoSansJP-Regular.ttf"), "").unwrap();
+
+        let found = find_font_files(&[user.clone(), system.clone(), system.join("missing")]);
+        assert_eq!(
+            found["NotoSansJP-Regular.ttf"],
+            user.join("NotoSansJP-Regular.ttf")
+        );
+        assert_eq!(
+            found["NotoSansCJK-Regular.ttc"],
+            system.join("opentype/noto/NotoSansCJK-Regular.ttc")
+        );
+    }
+
+    #[test]
+    fn traditional_chinese_has_its_own_font() {
+        let font = |code: &str| {
+            KNOWN
+                .iter()
+                .find(|language| language.tesseract == code)
+                .and_then(|language| language.font)
+                .map(|font| font.name)
+        };
+        assert_eq!(font("chi_tra"), Some("noto_sans_tc"));
+        assert_eq!(font("chi_sim"), Some("noto_sans_sc"));
+    }
+}
@@ -1,236 +1,871 @@
 mod app;
+mod cache;
+mod capture;
+mod clipboard;
+mod config;
+mod daemon;
+mod export;
+mod history;
+mod languages;
+mod normalize;
 mod ocr;
+mod output;
+mod paths;
+mod portal;
+mod preprocess;
+mod review;
+mod script;
+mod stardict;
+mod tessdata;
 mod translation;
+mod vocab;
+mod window_state;
 
 use app::OcrApp;
-use clap::{Parser, ValueEnum};
+use cache::{Lookup, TranslationCache};
+use capture::CaptureBackendKind;
+use clap::{Parser, Subcommand};
+use clipboard::{ClipboardContent, Source};
+use config::Config;
+use daemon::DaemonOptions;
 use eframe::egui;
-use include_dir::{include_dir, Dir};
-use regex::Regex;
-use std::env;
-use std::fs;
+use export::{ExportFormat, ExportNote, ExportSource};
+use history::{History, HistoryFilter};
+use languages::Registry;
+use ocr::{OcrResult, OcrSettings, Psm};
+use output::OutputFormat;
+use preprocess::{PreprocessOptions, PreprocessStep};
+use review::ReviewApp;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
-use translation::{is_single_word, CombinedTranslationData};
+use std::time::Duration;
+use translation::{ProviderKind, ProviderOptions, Translator};
+use vocab::Vocabulary;
 
-// Embed the 'tessdata' directory directly into the binary.
-// This requires a `tessdata` folder in the project's root directory.
-static TESS_DATA_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/tessdata");
+/// A simple OCR and translation tool
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Read settings from this file instead of `config.toml` in the config directory.
+    #[arg(long, value_name = "PATH", global = true)]
+    config: Option<PathBuf>,
+
+    /// Language(s) for OCR (Tesseract), e.g. `jpn`, `ja` or `eng+tha`. 'auto' uses all installed
+    /// languages except the target language; `langs list` shows them. [default: auto]
+    #[arg(long, value_name = "LANG", global = true)]
+    ocr_lang: Option<String>,
+
+    /// Also use the `.traineddata` files in this directory (e.g. /usr/share/tesseract/tessdata),
+    /// next to the bundled languages.
+    #[arg(long, value_name = "DIR", global = true)]
+    tessdata_dir: Option<PathBuf>,
+
+    /// Target language for translation [default: th]
+    #[arg(short, long, global = true)]
+    target: Option<String>,
+
+    /// Print the result to stdout in this format. `translate` defaults to `text`;
+    /// `capture` only prints when this is given.
+    #[arg(long, value_enum, global = true)]
+    output: Option<OutputFormat>,
+
+    /// Where `capture` takes its text from: a screen region (OCR), the clipboard, or the
+    /// primary selection. An image on the clipboard is run through OCR. [default: screen]
+    #[arg(long, value_enum, global = true)]
+    source: Option<Source>,
+
+    /// OCR this image file instead of taking a screenshot. Use '-' to read the image from stdin.
+    #[arg(long, value_name = "PATH", global = true)]
+    image: Option<PathBuf>,
+
+    /// Screen capture backend. 'auto' probes Spectacle, grim, X11 and then the portal.
+    /// [default: auto]
+    #[arg(long, value_enum, global = true)]
+    capture_backend: Option<CaptureBackendKind>,
+
+    /// OCR words below this confidence (0-100) are dropped as noise.
+    /// [default: 30 for Thai, Japanese, Korean and Chinese alone, otherwise 40]
+    #[arg(long, global = true)]
+    min_confidence: Option<f32>,
+
+    /// Comma-separated image preprocessing steps run before OCR, or 'none'.
+    /// [default: upscale,grayscale,invert,pad]
+    #[arg(long, value_enum, value_delimiter = ',', global = true)]
+    preprocess: Option<Vec<PreprocessStep>>,
+
+    /// Page segmentation mode: how Tesseract splits the capture into text. 'auto' picks word,
+    /// line, block or vertical from the shape of the capture. [default: auto]
+    #[arg(long, value_enum, global = true)]
+    psm: Option<Psm>,
+
+    /// Scale factor of the screen (2 for HiDPI), so `--psm auto` still recognizes single
+    /// lines. [default: GDK_SCALE or QT_SCALE_FACTOR, else 1]
+    #[arg(long, global = true)]
+    scale_factor: Option<f32>,
+
+    /// Rejoin hyphenated words and wrapped lines in the OCR text and fix confusions like
+    /// `0`/`O` before translating. [default]
+    #[arg(long, global = true, overrides_with = "no_normalize")]
+    normalize: bool,
+
+    /// Translate the OCR text as Tesseract read it.
+    #[arg(long, global = true, overrides_with = "normalize")]
+    no_normalize: bool,
+
+    /// Write the preprocessed image to this PNG file, to see what Tesseract receives.
+    #[arg(long, value_name = "PATH", global = true)]
+    preprocess_debug: Option<PathBuf>,
+
+    /// Print the result without opening the window, as `--output text` unless another
+    /// format is given.
+    #[arg(long, global = true)]
+    no_window: bool,
+
+    /// Start with the window pinned: it stays on top, survives focus loss and
+    /// reopens where it was last closed. Toggle with the Pin button or Ctrl+P.
+    #[arg(long, global = true, overrides_with = "no_pin")]
+    pin: bool,
+
+    /// Start with the window unpinned, even if the config file sets `pin = true`.
+    #[arg(long, global = true, overrides_with = "pin")]
+    no_pin: bool,
 
-#[derive(Clone, Debug, ValueEnum, PartialEq)]
-enum OcrLang {
-    Auto,
-    Eng,
-    Rus,
-    Kor,
-    Jpn,
-    #[value(name = "chi_sim")]
-    ChiSim,
-    #[value(name = "thai")]
-    Thai,
+    /// Comma-separated translation providers, run in this order. [default: google,longdo,stardict]
+    #[arg(long = "provider", value_enum, value_delimiter = ',', global = true)]
+    providers: Option<Vec<ProviderKind>>,
+
+    /// Base URL of a LibreTranslate-compatible server, e.g. http://192.168.1.10:5000
+    #[arg(long, value_name = "URL", global = true)]
+    libretranslate_url: Option<String>,
+
+    /// API key for the LibreTranslate server, if it requires one.
+    #[arg(long, value_name = "KEY", global = true)]
+    libretranslate_api_key: Option<String>,
+
+    /// Directory with StarDict dictionaries (.ifo/.idx/.dict[.dz]), searched recursively.
+    /// Defaults to `stardict` in the data directory.
+    #[arg(long, value_name = "DIR", global = true)]
+    stardict_dir: Option<PathBuf>,
+
+    /// Timeout for each network request, in seconds. [default: 10]
+    #[arg(long, global = true)]
+    timeout_secs: Option<u64>,
+
+    /// Always look translations up over the network and do not store them.
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Do not record lookups in the history.
+    #[arg(long, global = true)]
+    no_history: bool,
+
+    /// Cached translations older than this are refreshed (but still used when offline).
+    /// [default: 30]
+    #[arg(long, global = true)]
+    cache_ttl_days: Option<u64>,
+
+    /// Maximum number of cached translations; the oldest are removed first. [default: 5000]
+    #[arg(long, global = true)]
+    cache_max_entries: Option<usize>,
 }
 
-impl OcrLang {
-    /// Converts the enum variant to the string representation Tesseract expects.
-    fn to_tesseract_str(&self) -> &str {
-        match self {
-            OcrLang::Auto => "auto", // This isn't sent to Tesseract directly.
-            OcrLang::Eng => "eng",
-            OcrLang::Rus => "rus",
-            OcrLang::Kor => "kor",
-            OcrLang::Jpn => "jpn",
-            OcrLang::ChiSim => "chi_sim",
-            OcrLang::Thai => "tha", // Tesseract uses 'tha' for Thai
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Capture a screen region (or read `--image`), OCR it and show the result window (the default).
+    Capture,
+    /// Translate text without taking a screenshot or opening a window.
+    Translate {
+        /// Text to translate. Use '-' to read it from stdin.
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+    /// Inspect or clear the translation cache.
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// List past lookups, newest first.
+    History {
+        /// Only show lookups whose text or translation contains this.
+        search: Option<String>,
+        /// Only show lookups from or to this language, e.g. `en`.
+        #[arg(long)]
+        lang: Option<String>,
+        /// Maximum number of lookups to show.
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Delete the whole history instead of listing it.
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Review the saved vocabulary words that are due, with spaced repetition.
+    Review,
+    /// Export saved words or past lookups for Anki, as TSV or as an .apkg deck.
+    Export {
+        /// File to write. A `.apkg` extension selects the Anki package format.
+        path: PathBuf,
+        /// What to export.
+        #[arg(long, value_enum, default_value = "vocab")]
+        from: ExportSource,
+        /// Output format; guessed from the file extension if not given.
+        #[arg(long, value_enum)]
+        format: Option<ExportFormat>,
+        /// Name of the Anki deck the cards are imported into (for .apkg).
+        #[arg(long, default_value = "Floating Dictionary")]
+        deck: String,
+    },
+    /// Run in the background with a warm OCR engine, serving requests over D-Bus.
+    Daemon {
+        /// Global shortcut that captures and translates, registered through the
+        /// GlobalShortcuts portal. The desktop may ask to confirm or change it.
+        #[arg(long, value_name = "TRIGGER", default_value = "CTRL+ALT+D")]
+        shortcut: String,
+        /// Do not register a global shortcut.
+        #[arg(long)]
+        no_shortcut: bool,
+    },
+    /// Ask a running daemon to capture, translate or show the history.
+    Client {
+        #[command(subcommand)]
+        action: ClientAction,
+    },
+    /// Show or check the configuration file.
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// List, install or remove OCR languages (Tesseract `.traineddata` files).
+    Langs {
+        #[command(subcommand)]
+        action: LangsAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ClientAction {
+    /// Capture a screen region and show the result window.
+    Capture,
+    /// Translate text and show the result window.
+    Translate {
+        /// Text to translate.
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+    /// Show the lookup history window.
+    History,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print the effective settings (the config file plus command-line flags) as TOML.
+    Print,
+    /// Check the config file for errors.
+    Validate,
+}
+
+#[derive(Subcommand, Debug)]
+enum LangsAction {
+    /// List the languages in the tessdata directory.
+    List,
+    /// Copy a `.traineddata` file (e.g. from github.com/tesseract-ocr/tessdata_fast)
+    /// into the tessdata directory.
+    Install {
+        /// The file to install; its name gives the language code, e.g. `vie.traineddata`.
+        file: PathBuf,
+    },
+    /// Remove an installed language by its code, e.g. `vie`. Bundled languages stay.
+    Remove {
+        /// Language code, e.g. `vie`.
+        code: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Remove every cached translation.
+    Clear,
+    /// Show the number and size of cached translations.
+    Stats,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let config_path = match &args.config {
+        Some(path) => path.clone(),
+        None => Config::default_path()?,
+    };
+    if let Some(Command::Config { action }) = &args.command {
+        return run_config(action, &args, &config_path);
+    }
+    let mut config = Config::load(&config_path)?;
+    apply_args(&mut config, &args);
+    let cache = open_cache(&config);
+
+    match &args.command {
+        Some(Command::Translate { text }) => {
+            let format = args.output.unwrap_or(OutputFormat::Text);
+            let lookup = build_lookup(&config, cache)?;
+            run_translate(text, format, &lookup).await
+        }
+        Some(Command::Cache { action }) => run_cache(action, &config),
+        Some(Command::History {
+            search,
+            lang,
+            limit,
+            clear,
+        }) => {
+            let filter = HistoryFilter {
+                search: search.clone().unwrap_or_default(),
+                lang: lang.clone().unwrap_or_default(),
+            };
+            let format = args.output.unwrap_or(OutputFormat::Text);
+            run_history(&filter, *limit, *clear, format, &config)
+        }
+        Some(Command::Review) => run_review(&config),
+        Some(Command::Export {
+            path,
+            from,
+            format,
+            deck,
+        }) => {
+            let format = format.unwrap_or_else(|| ExportFormat::from_path(path));
+            run_export(path, *from, format, deck, &config)
+        }
+        Some(Command::Daemon {
+            shortcut,
+            no_shortcut,
+        }) => {
+            let lookup = build_lookup(&config, cache)?;
+            let shortcut = (!no_shortcut).then(|| shortcut.clone());
+            run_daemon(&args, &config, lookup, shortcut).await
         }
+        Some(Command::Client { action }) => run_client(action, &args).await,
+        Some(Command::Config { .. }) => unreachable!("handled before loading the config"),
+        Some(Command::Langs { action }) => run_langs(action, &config),
+        Some(Command::Capture) | None => {
+            let lookup = build_lookup(&config, cache)?;
+            run_capture(&args, &config, lookup).await
+        }
+    }
+}
+
+/// Overrides the config file settings with the flags given on the command line.
+fn apply_args(config: &mut Config, args: &Args) {
+    if let Some(ocr_lang) = &args.ocr_lang {
+        config.ocr_lang = ocr_lang.clone();
+    }
+    if args.tessdata_dir.is_some() {
+        config.tessdata_dir = args.tessdata_dir.clone();
+    }
+    if let Some(target) = &args.target {
+        config.target = target.clone();
+    }
+    if let Some(pin) = flag(args.pin, args.no_pin) {
+        config.pin = pin;
     }
 
-    /// Returns a list of all Tesseract-compatible language strings.
-    fn all_tesseract_langs() -> Vec<&'static str> {
-        vec!["eng", "rus", "kor", "jpn", "chi_sim", "tha"]
+    let capture = &mut config.capture;
+    if let Some(backend) = args.capture_backend {
+        capture.backend = backend;
+    }
+    if let Some(source) = args.source {
+        capture.source = source;
+    }
+    if args.min_confidence.is_some() {
+        capture.min_confidence = args.min_confidence;
+    }
+    if let Some(steps) = &args.preprocess {
+        capture.preprocess = steps.clone();
+    }
+    if let Some(psm) = args.psm {
+        capture.psm = psm;
+    }
+    if args.scale_factor.is_some() {
+        capture.scale_factor = args.scale_factor;
+    }
+    if let Some(normalize) = flag(args.normalize, args.no_normalize) {
+        capture.normalize = normalize;
+    }
+
+    let translation = &mut config.translation;
+    if let Some(providers) = &args.providers {
+        translation.providers = providers.clone();
+    }
+    if let Some(timeout_secs) = args.timeout_secs {
+        translation.timeout_secs = timeout_secs;
+    }
+    if args.libretranslate_url.is_some() {
+        translation.libretranslate_url = args.libretranslate_url.clone();
+    }
+    if args.libretranslate_api_key.is_some() {
+        translation.libretranslate_api_key = args.libretranslate_api_key.clone();
+    }
+    if args.stardict_dir.is_some() {
+        translation.stardict_dir = args.stardict_dir.clone();
+    }
+
+    if args.no_cache {
+        config.cache.enabled = false;
+    }
+    if let Some(ttl_days) = args.cache_ttl_days {
+        config.cache.ttl_days = ttl_days;
+    }
+    if let Some(max_entries) = args.cache_max_entries {
+        config.cache.max_entries = max_entries;
+    }
+    if args.no_history {
+        config.history.enabled = false;
     }
 }
 
-/// A simple OCR and translation tool
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
-struct Args {
-    /// Language for OCR (Tesseract). 'auto' uses all available languages except the target language.
-    #[arg(long, value_enum, default_value = "auto")]
-    ocr_lang: OcrLang,
+/// The value of a `--flag` / `--no-flag` pair, or `None` when neither was given.
+fn flag(yes: bool, no: bool) -> Option<bool> {
+    match (yes, no) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+fn run_config(
+    action: &ConfigAction,
+    args: &Args,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let exists = path.exists();
+
+    match action {
+        ConfigAction::Print => {
+            let mut config = Config::load(path)?;
+            apply_args(&mut config, args);
+            if exists {
+                println!("# Settings from {}", path.display());
+            } else {
+                println!(
+                    "# {} does not exist; these are the defaults",
+                    path.display()
+                );
+            }
+            config.redact_secrets();
+            print!("{}", config.to_toml()?);
+        }
+        ConfigAction::Validate => {
+            if !exists {
+                println!("{} does not exist; the defaults are used.", path.display());
+                return Ok(());
+            }
+            let problems = Config::load(path)?.validate();
+            if !problems.is_empty() {
+                for problem in &problems {
+                    eprintln!("{}: {}", path.display(), problem);
+                }
+                return Err(format!("The config file has {} problem(s).", problems.len()).into());
+            }
+            println!("{} is valid.", path.display());
+        }
+    }
 
-    /// Target language for translation
-    #[arg(short, long, default_value = "th")]
-    target: String,
+    Ok(())
 }
 
-/// Sets up the Tesseract data directory.
-/// It extracts embedded `.traineddata` files to a user-specific data directory
-/// and sets the TESSDATA_PREFIX environment variable so Tesseract can find them.
-fn setup_tessdata() -> Result<(), Box<dyn std::error::Error>> {
-    // Find a suitable directory to store our data files (e.g., ~/.local/share on Linux).
-    let data_dir = dirs::data_dir().ok_or("Could not find a valid data directory.")?;
-    let app_data_dir = data_dir.join("floating-dictionary-linux");
-    let tessdata_path = app_data_dir.join("tessdata");
+fn run_langs(action: &LangsAction, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tessdata::setup(config.tessdata_dir.as_deref())?;
 
-    // If the directory doesn't exist, create it and extract the embedded files.
-    if !tessdata_path.exists() {
-        fs::create_dir_all(&tessdata_path)?;
+    match action {
+        LangsAction::List => {
+            let registry = Registry::load(&dir)?;
+            println!("Location: {}", dir.display());
+            for pack in tessdata::list(&dir)? {
+                let (name, code, script) = match registry.find(&pack.code) {
+                    Some(language) => (
+                        language.name.as_ref(),
+                        language.code.unwrap_or("-"),
+                        format!("{:?}", language.script),
+                    ),
+                    None => ("-", "-", "-".to_string()),
+                };
+                println!(
+                    "{:<10} {:<6} {:<22} {:<9} {:>6.1} MiB  {}",
+                    pack.code,
+                    code,
+                    name,
+                    script,
+                    pack.size as f64 / (1024.0 * 1024.0),
+                    if pack.bundled {
+                        "bundled"
+                    } else if pack.linked {
+                        "linked"
+                    } else {
+                        "installed"
+                    }
+                );
+            }
+        }
+        LangsAction::Install { file } => {
+            let code = tessdata::install(&dir, file)?;
+            println!("Installed '{}' into {}.", code, dir.display());
+        }
+        LangsAction::Remove { code } => {
+            tessdata::remove(&dir, code)?;
+            println!("Removed '{}' from {}.", code, dir.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn build_lookup(
+    config: &Config,
+    cache: Option<TranslationCache>,
+) -> Result<Lookup, Box<dyn std::error::Error>> {
+    Ok(Lookup {
+        translator: build_translator(config)?,
+        cache,
+        history: open_history(config),
+        target: config.target.clone(),
+        runtime: tokio::runtime::Handle::current(),
+    })
+}
+
+fn build_translator(config: &Config) -> Result<Translator, Box<dyn std::error::Error>> {
+    let translation = &config.translation;
+    let options = ProviderOptions {
+        timeout: Duration::from_secs(translation.timeout_secs),
+        libretranslate_url: translation.libretranslate_url.clone(),
+        libretranslate_api_key: translation.libretranslate_api_key.clone(),
+        stardict_dir: translation.stardict_dir.clone(),
+        longdo_dictionaries: translation.longdo_dictionaries.clone(),
+    };
+    Translator::new(&translation.providers, &options)
+}
 
-        for file in TESS_DATA_DIR.files() {
-            let file_path = tessdata_path.join(file.path());
-            fs::write(file_path, file.contents())?;
+/// Opens the translation cache unless it was disabled.
+/// A cache that cannot be opened only disables caching; lookups still work.
+fn open_cache(config: &Config) -> Option<TranslationCache> {
+    if !config.cache.enabled {
+        return None;
+    }
+    let ttl = Duration::from_secs(config.cache.ttl_days * 24 * 60 * 60);
+    TranslationCache::open(ttl, config.cache.max_entries).ok()
+}
+
+/// Opens the lookup history unless it was disabled.
+/// A history that cannot be opened is skipped; lookups still work.
+fn open_history(config: &Config) -> Option<History> {
+    if !config.history.enabled {
+        return None;
+    }
+    History::open(config.history.max_entries).ok()
+}
+
+fn run_cache(action: &CacheAction, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let ttl = Duration::from_secs(config.cache.ttl_days * 24 * 60 * 60);
+    let cache = TranslationCache::open(ttl, config.cache.max_entries)?;
+
+    match action {
+        CacheAction::Clear => {
+            let removed = cache.clear()?;
+            println!("Removed {} cached translations.", removed);
+        }
+        CacheAction::Stats => {
+            let stats = cache.stats()?;
+            println!("Location: {}", cache.dir().display());
+            println!("Entries:  {} ({} expired)", stats.entries, stats.expired);
+            println!("Size:     {:.1} KiB", stats.total_bytes as f64 / 1024.0);
         }
     }
 
-    // Tell Tesseract where to find the data files.
-    // The TESSDATA_PREFIX variable should point to the directory
-    // containing the `.traineddata` files directly.
-    env::set_var("TESSDATA_PREFIX", &tessdata_path);
+    Ok(())
+}
+
+fn run_history(
+    filter: &HistoryFilter,
+    limit: usize,
+    clear: bool,
+    format: OutputFormat,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let history = History::open(config.history.max_entries)?;
+
+    if clear {
+        let removed = history.clear()?;
+        println!(
+            "Removed {} lookups from {}.",
+            removed,
+            history.path().display()
+        );
+        return Ok(());
+    }
+
+    let entries: Vec<_> = history
+        .load()?
+        .into_iter()
+        .filter(|entry| filter.matches(entry))
+        .take(limit)
+        .collect();
+    output::print_history(&entries, format)?;
 
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // --- Phase 0: Setup ---
-    // Ensure Tesseract data files are available and the environment is configured.
-    setup_tessdata()?;
+/// Opens the review window for the saved words that are due today.
+fn run_review(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let vocabulary = Vocabulary::open()?;
+    let due = vocabulary.due_cards().len();
+    if due == 0 {
+        println!(
+            "No words are due for review ({} saved). Save words with the Save button in the result window.",
+            vocabulary.cards.len()
+        );
+        return Ok(());
+    }
 
-    let args = Args::parse();
+    let ui = config.ui.clone();
+    let viewport = egui::ViewportBuilder::default()
+        .with_inner_size([ui.width, 400.0])
+        .with_min_inner_size([400.0, 250.0]);
+    eframe::run_native(
+        "Floating Dictionary - Review",
+        app::native_options(viewport, &ui),
+        Box::new(move |cc| {
+            app::setup_fonts_and_style(&cc.egui_ctx, &ui);
+            Ok(Box::new(ReviewApp::new(vocabulary)))
+        }),
+    )?;
 
-    // --- OCR Language Selection Logic ---
-    let ocr_lang_str = if args.ocr_lang == OcrLang::Auto {
-        let all_langs = OcrLang::all_tesseract_langs();
-        let target_tess_lang = match args.target.as_str() {
-            "th" => "tha",
-            "en" => "eng",
-            "ru" => "rus",
-            "ko" => "kor",
-            "ja" => "jpn",
-            "zh-CN" => "chi_sim",
-            _ => "",
-        };
-        let filtered_langs: Vec<&str> = all_langs
-            .into_iter()
-            .filter(|&lang| lang != target_tess_lang)
-            .collect();
-        filtered_langs.join("+")
+    Ok(())
+}
+
+fn run_export(
+    path: &Path,
+    from: ExportSource,
+    format: ExportFormat,
+    deck: &str,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let notes: Vec<ExportNote> = match from {
+        ExportSource::Vocab => Vocabulary::open()?
+            .cards
+            .iter()
+            .map(ExportNote::from_card)
+            .collect(),
+        ExportSource::History => {
+            export::notes_from_history(&History::open(config.history.max_entries)?.load()?)
+        }
+    };
+    if notes.is_empty() {
+        return Err("Nothing to export.".into());
+    }
+
+    match format {
+        ExportFormat::Tsv => export::write_tsv(&notes, path)?,
+        ExportFormat::Apkg => export::write_apkg(&notes, deck, path)?,
+    }
+    println!("Exported {} notes to {}.", notes.len(), path.display());
+
+    Ok(())
+}
+
+/// Loads the OCR engine once and serves lookups over D-Bus until killed.
+async fn run_daemon(
+    args: &Args,
+    config: &Config,
+    lookup: Lookup,
+    shortcut: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tessdata_dir = tessdata::setup(config.tessdata_dir.as_deref())?;
+
+    let options = DaemonOptions {
+        ocr: ocr_settings(config, &tessdata_dir)?,
+        preprocess: PreprocessOptions {
+            steps: config.capture.preprocess.clone(),
+            debug_path: args.preprocess_debug.clone(),
+        },
+        capture_backend: config.capture.backend,
+        pin: config.pin,
+        shortcut,
+        ui: config.ui.clone(),
+    };
+    daemon::run(lookup, options).await
+}
+
+/// Sends a request to a running daemon. The window opens in the daemon process.
+async fn run_client(action: &ClientAction, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let connection = zbus::Connection::session().await?;
+    let proxy = zbus::Proxy::new(
+        &connection,
+        daemon::BUS_NAME,
+        daemon::OBJECT_PATH,
+        daemon::INTERFACE,
+    )
+    .await?;
+
+    match action {
+        ClientAction::Capture => {
+            let text: String = proxy.call("CaptureAndTranslate", &()).await?;
+            println!("{}", text.trim());
+        }
+        ClientAction::Translate { text } => {
+            let json: String = proxy.call("TranslateText", &(text.join(" "), true)).await?;
+            let translation_data = serde_json::from_str(&json)?;
+            let format = args.output.unwrap_or(OutputFormat::Text);
+            output::print_translation(&translation_data, None, format)?;
+        }
+        ClientAction::History => proxy.call::<_, _, ()>("ShowHistory", &()).await?,
+    }
+
+    Ok(())
+}
+
+/// Translates text given on the command line (or stdin) and prints the result.
+/// No screenshot is taken and no window is opened.
+///
+/// With `--output ndjson` and stdin input, every non-empty line is translated
+/// and printed as its own record, so the command can sit in a pipeline.
+async fn run_translate(
+    text: &[String],
+    format: OutputFormat,
+    lookup: &Lookup,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let from_stdin = text.len() == 1 && text[0] == "-";
+    let input = if from_stdin {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer)?;
+        buffer
+    } else {
+        text.join(" ")
+    };
+
+    let queries: Vec<&str> = if from_stdin && format == OutputFormat::Ndjson {
+        input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect()
+    } else if input.trim().is_empty() {
+        Vec::new()
     } else {
-        args.ocr_lang.to_tesseract_str().to_owned()
+        vec![input.as_str()]
     };
 
-    // --- Phase 1: Capture and OCR (Async) ---
-    let mut ocr_text = ocr::capture_and_ocr(&ocr_lang_str).await?;
-    if is_single_word(&ocr_text) {
-        // For single words, trim any special characters from the start and end.
-        let re = Regex::new(r"^[^a-zA-Z0-9\p{L}]+|[^a-zA-Z0-9\p{L}]+$").unwrap();
-        ocr_text = re.replace_all(&ocr_text, "").to_string();
-    }
-
-    // Create a channel for sending the complex translation data
-    let (tx, rx) = channel::<CombinedTranslationData>();
-
-    // Start translating immediately in a background thread
-    let text_clone = ocr_text.clone();
-    let target_lang = args.target;
-    std::thread::spawn(move || {
-        let rt = tokio::runtime::Runtime::new().unwrap();
-        let translation_data = rt
-            .block_on(translation::translate_text(
-                &text_clone,
-                "auto", // Always use auto-detection for the Google Translate source language
-                &target_lang,
-            ))
-            .unwrap(); // Using unwrap here for simplicity, consider proper error handling
-        let _ = tx.send(translation_data);
-    });
+    if queries.is_empty() {
+        return Err("Nothing to translate.".into());
+    }
 
-    // --- Phase 2: Show Results in UI (Sync) ---
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([500.0, 200.0])
-            .with_min_inner_size([400.0, 150.0])
-            .with_max_inner_size([800.0, 800.0])
-            .with_decorations(true)
-            .with_transparent(false)
-            .with_resizable(true),
-        ..Default::default()
+    for query in queries {
+        let translation_data = lookup.translate(query).await?;
+        output::print_translation(&translation_data, None, format)?;
+    }
+
+    Ok(())
+}
+
+/// Resolves `ocr_lang` against the installed languages. In `auto` mode, each
+/// capture is recognized with only the languages of the scripts it contains.
+fn ocr_settings(
+    config: &Config,
+    tessdata_dir: &Path,
+) -> Result<OcrSettings, Box<dyn std::error::Error>> {
+    let registry = Registry::load(tessdata_dir)?;
+    let languages = registry
+        .resolve(&config.ocr_lang, &config.target)?
+        .into_iter()
+        .cloned()
+        .collect();
+    Ok(OcrSettings {
+        languages,
+        detect_script: config.ocr_lang == "auto",
+        min_confidence: config.capture.min_confidence,
+        psm: config.capture.psm,
+        scale_factor: config
+            .capture
+            .scale_factor
+            .unwrap_or_else(capture::scale_factor),
+        normalize: config.capture.normalize,
+    })
+}
+
+/// Runs OCR on an encoded image with the `ocr_lang`, `preprocess` and
+/// `min_confidence` settings.
+fn recognize(
+    args: &Args,
+    config: &Config,
+    image_data: &[u8],
+) -> Result<OcrResult, Box<dyn std::error::Error>> {
+    // Ensure Tesseract data files are available and the environment is configured.
+    let tessdata_dir = tessdata::setup(config.tessdata_dir.as_deref())?;
+    let ocr_settings = ocr_settings(config, &tessdata_dir)?;
+
+    let preprocess_options = PreprocessOptions {
+        steps: config.capture.preprocess.clone(),
+        debug_path: args.preprocess_debug.clone(),
     };
+    let image = preprocess::decode(image_data)?;
+    let capture_size = (image.width(), image.height());
+    let image = preprocess::preprocess(image, &preprocess_options)?;
+    let ocr_result = ocr::ocr_image(&image, &ocr_settings, capture_size)?;
+    if ocr_result.text.trim().is_empty() {
+        return Err("No text was recognized in the selected region.".into());
+    }
+    Ok(ocr_result)
+}
 
-    eframe::run_native(
-        "Floating Dictionary",
-        options,
-        Box::new(|cc| {
-            // --- FONT & STYLE SETUP ---
-            let mut fonts = egui::FontDefinitions::default();
-            fonts.font_data.insert(
-                "noto_sans".to_owned(),
-                egui::FontData::from_static(include_bytes!("../assets/fonts/NotoSans-Regular.ttf")),
-            );
-            fonts.font_data.insert(
-                "noto_sans_thai".to_owned(),
-                egui::FontData::from_static(include_bytes!(
-                    "../assets/fonts/NotoSansThai-Regular.ttf"
-                )),
-            );
-            fonts.font_data.insert(
-                "noto_sans_jp".to_owned(),
-                egui::FontData::from_static(include_bytes!(
-                    "../assets/fonts/NotoSansJP-Regular.ttf"
-                )),
-            );
-            fonts.font_data.insert(
-                "noto_sans_kr".to_owned(),
-                egui::FontData::from_static(include_bytes!(
-                    "../assets/fonts/NotoSansKR-Regular.ttf"
-                )),
-            );
-            fonts.font_data.insert(
-                "noto_sans_sc".to_owned(),
-                egui::FontData::from_static(include_bytes!(
-                    "../assets/fonts/NotoSansSC-Regular.ttf"
-                )),
-            );
-            let font_family_list = vec![
-                "noto_sans".to_owned(),
-                "noto_sans_thai".to_owned(),
-                "noto_sans_jp".to_owned(),
-                "noto_sans_kr".to_owned(),
-                "noto_sans_sc".to_owned(),
-            ];
-            fonts
-                .families
-                .insert(egui::FontFamily::Proportional, font_family_list.clone());
-            fonts
-                .families
-                .insert(egui::FontFamily::Monospace, font_family_list);
-            cc.egui_ctx.set_fonts(fonts);
-            let mut style = (*cc.egui_ctx.style()).clone();
-            style.text_styles = [
-                (
-                    egui::TextStyle::Body,
-                    egui::FontId::new(16.0, egui::FontFamily::Proportional),
-                ),
-                (
-                    egui::TextStyle::Button,
-                    egui::FontId::new(14.0, egui::FontFamily::Proportional),
-                ),
-                (
-                    egui::TextStyle::Small,
-                    egui::FontId::new(12.0, egui::FontFamily::Proportional),
-                ),
-                (
-                    egui::TextStyle::Heading,
-                    egui::FontId::new(24.0, egui::FontFamily::Proportional),
-                ),
-            ]
-            .into();
-            style.spacing.item_spacing = egui::Vec2::new(6.0, 6.0);
-            cc.egui_ctx.set_style(style);
-
-            // Use the new constructor for OcrApp
-            Ok(Box::new(OcrApp::new(ocr_text, rx)))
-        }),
-    )?;
+/// Captures a screen region (or reads the `--image` file, the clipboard or the
+/// primary selection), runs OCR on it if needed and shows the translation window.
+async fn run_capture(
+    args: &Args,
+    config: &Config,
+    lookup: Lookup,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // --- Phase 1: Get the text, with OCR if it is an image (Async) ---
+    let image_data = match (&args.image, config.capture.source) {
+        (Some(path), _) => ocr::read_image(path)?,
+        (None, Source::Screen) => capture::capture_image(config.capture.backend).await?,
+        (None, source) => match clipboard::read(source)? {
+            ClipboardContent::Text(text) => {
+                return show_lookup(args, config, lookup, text, None).await
+            }
+            ClipboardContent::Image(image_data) => image_data,
+        },
+    };
+    let ocr_result = recognize(args, config, &image_data)?;
+    let ocr_text = ocr_result.text.clone();
+    show_lookup(args, config, lookup, ocr_text, Some(ocr_result)).await
+}
+
+/// Translates the text and prints it (with `--output`) and/or shows the result window.
+async fn show_lookup(
+    args: &Args,
+    config: &Config,
+    lookup: Lookup,
+    text: String,
+    ocr_result: Option<OcrResult>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Without a window the result has to go somewhere, so it is printed as text.
+    let format = args.output.or(args.no_window.then_some(OutputFormat::Text));
+    let rx = if let Some(format) = format {
+        // The result is needed on stdout, so wait for it here instead of in the background.
+        let translation_data = lookup.translate(&text).await?;
+        output::print_translation(&translation_data, ocr_result.as_ref(), format)?;
+
+        if args.no_window {
+            return Ok(());
+        }
+        let (tx, rx) = channel();
+        let _ = tx.send(Ok(translation_data));
+        rx
+    } else {
+        // Start translating immediately in a background thread
+        lookup.spawn(&text)
+    };
+
+    // --- Phase 2: Show Results in UI (Sync) ---
+    let app = OcrApp::new(text, ocr_result, lookup, rx, config.pin);
+    app::run_window(app, &config.ui)?;
 
     Ok(())
 }
@@ -0,0 +1,488 @@
+use crate::script::Script;
+use std::ops::Range;
+
+/// Ligatures Tesseract sometimes outputs instead of the separate letters.
+const LIGATURES: &[(char, &str)] = &[
+    ('ﬀ', "ff"),
+    ('ﬁ', "fi"),
+    ('ﬂ', "fl"),
+    ('ﬃ', "ffi"),
+    ('ﬄ', "ffl"),
+    ('ﬅ', "st"),
+    ('ﬆ', "st"),
+];
+
+/// Hyphens that can split a word at the end of a line, the soft hyphen included.
+const HYPHENS: &[char] = &['-', '\u{00AD}', '\u{2010}'];
+
+/// A line ending in one of these ends a sentence, so the next line starts a new one.
+const SENTENCE_ENDS: &[char] = &['.', '!', '?', ':', '…', '。', '！', '？', '：'];
+
+/// Closing quotes and brackets that may follow the end of a sentence.
+const CLOSERS: &[char] = &['"', '\'', ')', ']', '”', '’', '」', '』', '）'];
+
+/// Cleans up OCR text before it is translated:
+/// - ligatures are replaced by their letters (`ﬁ` → `fi`),
+/// - digits inside words and letters inside numbers are fixed (`l0ve` → `love`, `2O19` → `2019`),
+/// - spaces between Chinese or Japanese characters are removed, and in Thai
+///   those that split a word apart,
+/// - words hyphenated at the end of a line are joined again,
+/// - wrapped lines are merged into sentences; a line break is kept after the
+///   end of a sentence and before a list item.
+pub fn normalize(text: &str) -> String {
+    let text = replace_ligatures(text);
+    let lines: Vec<String> = text
+        .lines()
+        .map(clean_line)
+        .filter(|line| !line.is_empty())
+        .collect();
+    join_lines(&lines)
+}
+
+// --- Lines ---
+
+/// Fixes the words of one line and joins them with single spaces, leaving
+/// out the stray ones (see `is_stray_space`).
+fn clean_line(line: &str) -> String {
+    let mut cleaned = String::new();
+    for word in line.split_whitespace().map(fix_confusions) {
+        let before = cleaned.rsplit(' ').next().unwrap_or_default();
+        if !cleaned.is_empty() && !is_stray_space(before, &word) {
+            cleaned.push(' ');
+        }
+        cleaned.push_str(&word);
+    }
+    cleaned
+}
+
+/// Whether OCR put a space between `before` and `after` that the text does not
+/// have. Chinese and Japanese are written without spaces. Thai uses them
+/// between phrases and sentences, so there a space only goes when one side is
+/// a fragment that cannot stand alone (see `is_thai_fragment`).
+fn is_stray_space(before: &str, after: &str) -> bool {
+    let (Some(last), Some(first)) = (before.chars().last(), after.chars().next()) else {
+        return false;
+    };
+    if !(is_spaceless(last) && is_spaceless(first)) {
+        return false;
+    }
+    match (is_thai(last), is_thai(first)) {
+        (false, false) => true,
+        (true, true) => is_thai_fragment(before, after),
+        _ => false,
+    }
+}
+
+/// A single character, a leading vowel (`เ`, `แ`, `โ`, `ใ`, `ไ`) at the end of
+/// `before`, or a vowel or tone mark at the start of `after`: none of these
+/// ends or starts a Thai word.
+fn is_thai_fragment(before: &str, after: &str) -> bool {
+    let single = |chunk: &str| chunk.chars().count() == 1;
+    let leading_vowel = before
+        .chars()
+        .last()
+        .is_some_and(|c| matches!(c, '\u{0E40}'..='\u{0E44}'));
+    let dependent = after.chars().next().is_some_and(|c| {
+        matches!(
+            c,
+            '\u{0E30}'..='\u{0E3A}' | '\u{0E45}' | '\u{0E47}'..='\u{0E4E}'
+        )
+    });
+    single(before) || single(after) || leading_vowel || dependent
+}
+
+fn join_lines(lines: &[String]) -> String {
+    let mut text = String::new();
+    for line in lines {
+        let Some(last) = text.chars().last() else {
+            text.push_str(line);
+            continue;
+        };
+        let first = line.chars().next().unwrap_or(' ');
+        let before_hyphen = text.chars().rev().nth(1).unwrap_or(' ');
+
+        if HYPHENS.contains(&last) && before_hyphen.is_alphabetic() && first.is_alphabetic() {
+            // "trans-" + "lation" is one word; "Jean-" + "Paul" keeps its hyphen.
+            if first.is_lowercase() {
+                text.pop();
+            }
+        } else if ends_sentence(&text) || starts_list_item(line) {
+            text.push('\n');
+        } else if !(is_spaceless(last) && is_spaceless(first)) {
+            // A wrapped line of Chinese, Japanese or Thai continues without a
+            // space; Thai breaks lines inside phrases, not at their spaces.
+            text.push(' ');
+        }
+        text.push_str(line);
+    }
+    text
+}
+
+fn ends_sentence(text: &str) -> bool {
+    text.trim_end_matches(CLOSERS)
+        .chars()
+        .last()
+        .is_some_and(|c| SENTENCE_ENDS.contains(&c))
+}
+
+/// Bullets (`•`, `-`, `*`) and numbered items (`1.`, `2)`).
+fn starts_list_item(line: &str) -> bool {
+    if line.starts_with(['•', '·', '*', '-', '–']) {
+        return true;
+    }
+    let rest = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    rest.len() < line.len() && (rest.starts_with(". ") || rest.starts_with(") "))
+}
+
+// --- Characters ---
+
+fn replace_ligatures(text: &str) -> String {
+    let mut replaced = String::with_capacity(text.len());
+    for c in text.chars() {
+        match LIGATURES.iter().find(|(ligature, _)| *ligature == c) {
+            Some((_, letters)) => replaced.push_str(letters),
+            None => replaced.push(c),
+        }
+    }
+    replaced
+}
+
+/// Characters of Chinese, Japanese and Thai, and the full-width CJK punctuation.
+fn is_spaceless(c: char) -> bool {
+    Script::of_char(c).is_some_and(Script::is_spaceless)
+        || matches!(c as u32, 0x3000..=0x303F | 0xFF01..=0xFF60)
+}
+
+fn is_thai(c: char) -> bool {
+    Script::of_char(c) == Some(Script::Thai)
+}
+
+/// Fixes `0`/`O` and `1`/`l` mix-ups, judged by the rest of the word: digits
+/// between the letters of a word become letters, letters in a number become
+/// digits. Words that could be either (`O2`, `1st`, `10am`, `user1`) are left alone.
+/// Every change stays within `0`/`O`/`o` or `1`/`l`/`I` (see `char_spans`).
+fn fix_confusions(word: &str) -> String {
+    // Only the core of the word counts; punctuation around it stays as it is.
+    let core = word.trim_matches(|c: char| !c.is_alphanumeric());
+    if core.is_empty() {
+        return word.to_string();
+    }
+    let start = word.find(core).unwrap_or(0);
+    let (prefix, rest) = word.split_at(start);
+    let suffix = &rest[core.len()..];
+
+    let letters = core.chars().filter(|c| c.is_alphabetic()).count();
+    let other_letters = core
+        .chars()
+        .filter(|c| c.is_alphabetic() && !matches!(c, 'O' | 'o' | 'l' | 'I' | 'О' | 'о'))
+        .count();
+    let digits = core.chars().filter(|c| c.is_ascii_digit()).count();
+    let other_digits = core
+        .chars()
+        .filter(|c| c.is_ascii_digit() && !matches!(c, '0' | '1'))
+        .count();
+
+    let fixed: String = if letters >= 3 && other_digits == 0 && !has_digit_run(core) {
+        let chars: Vec<char> = core.chars().collect();
+        let cyrillic = chars
+            .iter()
+            .any(|c| Script::of_char(*c) == Some(Script::Cyrillic));
+        let upper = chars
+            .iter()
+            .filter(|c| c.is_alphabetic())
+            .all(|c| c.is_uppercase());
+        // A digit is only a misread letter inside a word (`l0ve`), at the start
+        // of a lowercase one (`1ike`), or a `0` ending a word whose letters all
+        // have the same case (`HELL0`). A `1` at the end is usually real (`user1`).
+        let same_case = |letters: &[char]| {
+            letters
+                .iter()
+                .all(|c| c.is_alphabetic() && c.is_uppercase())
+                || letters
+                    .iter()
+                    .all(|c| c.is_alphabetic() && c.is_lowercase())
+        };
+        let misread = |index: usize| {
+            let letter_at = |i: Option<usize>| {
+                i.and_then(|i| chars.get(i))
+                    .is_some_and(|c| c.is_alphabetic())
+            };
+            (letter_at(index.checked_sub(1)) && letter_at(Some(index + 1)))
+                || (index == 0
+                    && chars[1..]
+                        .iter()
+                        .all(|c| c.is_alphabetic() && c.is_lowercase()))
+                || (index == chars.len() - 1
+                    && chars[index] == '0'
+                    && digits == 1
+                    && same_case(&chars[..index]))
+        };
+        chars
+            .iter()
+            .enumerate()
+            .map(|(index, &c)| match (c, cyrillic, upper) {
+                _ if !misread(index) => c,
+                ('0', false, true) => 'O',
+                ('0', false, false) => 'o',
+                ('0', true, true) => 'О',
+                ('0', true, false) => 'о',
+                ('1', false, true) => 'I',
+                ('1', false, false) => 'l',
+                _ => c,
+            })
+            .collect()
+    } else if digits >= 2 && other_letters == 0 {
+        core.chars()
+            .map(|c| match c {
+                'O' | 'o' | 'О' | 'о' => '0',
+                'l' | 'I' => '1',
+                _ => c,
+            })
+            .collect()
+    } else {
+        return word.to_string();
+    };
+
+    format!("{}{}{}", prefix, fixed, suffix)
+}
+
+/// For each character of `raw`, the byte range of what it became in
+/// `normalized`, the output of `normalize` for it; `None` for the characters
+/// that were dropped (extra spaces, line-end hyphens, trimmed punctuation).
+/// Relies on `normalize` keeping the order of the characters and only
+/// changing them as described there.
+pub fn char_spans(raw: &str, normalized: &str) -> Vec<Option<Range<usize>>> {
+    let mut out = normalized.char_indices().peekable();
+    let mut spans = Vec::new();
+    for c in raw.chars() {
+        let span = if let Some((_, letters)) = LIGATURES.iter().find(|(l, _)| *l == c) {
+            let rest = &normalized[out.peek().map_or(normalized.len(), |(i, _)| *i)..];
+            rest.starts_with(letters).then(|| {
+                let start = normalized.len() - rest.len();
+                for _ in letters.chars() {
+                    out.next();
+                }
+                start..start + letters.len()
+            })
+        } else {
+            out.next_if(|(_, n)| same_char(c, *n))
+                .map(|(i, n)| i..i + n.len_utf8())
+        };
+        spans.push(span);
+    }
+    spans
+}
+
+/// Whether `normalize` may have turned `a` into `b`.
+fn same_char(a: char, b: char) -> bool {
+    const GROUPS: &[&[char]] = &[&['0', 'O', 'o', 'О', 'о'], &['1', 'l', 'I']];
+    a == b
+        || (a.is_whitespace() && b.is_whitespace())
+        || GROUPS
+            .iter()
+            .any(|group| group.contains(&a) && group.contains(&b))
+}
+
+/// Two digits in a row are a number (`10am`), not a misread letter.
+fn has_digit_run(text: &str) -> bool {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .windows(2)
+        .any(|pair| pair[0].is_ascii_digit() && pair[1].is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{char_spans, normalize};
+
+    // --- Latin ---
+
+    #[test]
+    fn latin_rejoins_hyphenated_words() {
+        assert_eq!(normalize("a trans-\nlation"), "a translation");
+        assert_eq!(
+            normalize("a soft trans\u{00AD}\nlation"),
+            "a soft translation"
+        );
+    }
+
+    #[test]
+    fn latin_keeps_hyphen_before_capital() {
+        assert_eq!(normalize("Jean-\nPaul"), "Jean-Paul");
+    }
+
+    #[test]
+    fn latin_merges_wrapped_lines() {
+        assert_eq!(
+            normalize("This sentence was\nwrapped by the\nlayout."),
+            "This sentence was wrapped by the layout."
+        );
+    }
+
+    #[test]
+    fn latin_keeps_break_after_sentence_and_before_list_items() {
+        assert_eq!(
+            normalize("First sentence.\nSecond one."),
+            "First sentence.\nSecond one."
+        );
+        assert_eq!(normalize("\"Quoted.\"\nNext"), "\"Quoted.\"\nNext");
+        assert_eq!(
+            normalize("Steps\n1. Open it\n2. Close it"),
+            "Steps\n1. Open it\n2. Close it"
+        );
+        assert_eq!(normalize("Items\n• one\n• two"), "Items\n• one\n• two");
+    }
+
+    #[test]
+    fn latin_fixes_digits_in_words() {
+        assert_eq!(normalize("l0ve"), "love");
+        assert_eq!(normalize("HELL0 W0RLD"), "HELLO WORLD");
+        assert_eq!(normalize("hell0"), "hello");
+        assert_eq!(normalize("1ike this,"), "like this,");
+        assert_eq!(normalize("F1LE"), "FILE");
+    }
+
+    #[test]
+    fn latin_fixes_letters_in_numbers() {
+        assert_eq!(normalize("in 2O19"), "in 2019");
+        assert_eq!(normalize("at 1O:3O"), "at 10:30");
+        assert_eq!(normalize("l00%"), "100%");
+    }
+
+    #[test]
+    fn latin_leaves_ambiguous_words() {
+        for text in ["O2", "CO2", "1st", "10am", "1kg", "MP3", "A4", "I", "10"] {
+            assert_eq!(normalize(text), text);
+        }
+    }
+
+    #[test]
+    fn latin_leaves_identifiers() {
+        for text in [
+            "user1",
+            "Step1",
+            "v1",
+            "ID01",
+            "Win10",
+            "1Password",
+            "Hell0",
+        ] {
+            assert_eq!(normalize(text), text);
+        }
+    }
+
+    #[test]
+    fn latin_replaces_ligatures() {
+        assert_eq!(normalize("ﬁnal ﬂow oﬃce"), "final flow office");
+    }
+
+    #[test]
+    fn latin_collapses_spaces() {
+        assert_eq!(normalize("  too   many  spaces \n\n"), "too many spaces");
+    }
+
+    #[test]
+    fn spans_follow_the_changes() {
+        let raw = "a trans-\nlati0n  ﬁle";
+        let normalized = normalize(raw);
+        assert_eq!(normalized, "a translation file");
+        let spans = char_spans(raw, &normalized);
+        let shown = |from: usize, to: usize| {
+            let mapped: Vec<_> = spans[from..to].iter().flatten().collect();
+            &normalized[mapped[0].start..mapped[mapped.len() - 1].end]
+        };
+        assert_eq!(shown(2, 8), "trans"); // "trans-"
+        assert_eq!(shown(9, 15), "lation"); // "lati0n"
+        assert_eq!(shown(17, 20), "file"); // "ﬁle"
+    }
+
+    // --- Cyrillic ---
+
+    #[test]
+    fn cyrillic_rejoins_hyphenated_words() {
+        assert_eq!(normalize("пере-\nвод текста"), "перевод текста");
+    }
+
+    #[test]
+    fn cyrillic_fixes_zero_with_cyrillic_letter() {
+        assert_eq!(normalize("Пр0ект"), "Проект");
+        assert_eq!(normalize("ПР0ЕКТ"), "ПРОЕКТ");
+    }
+
+    #[test]
+    fn cyrillic_merges_wrapped_lines() {
+        assert_eq!(
+            normalize("Это длинное\nпредложение."),
+            "Это длинное предложение."
+        );
+    }
+
+    // --- Japanese ---
+
+    #[test]
+    fn japanese_removes_spaces_between_characters() {
+        assert_eq!(normalize("日本 語 の 文章 です 。"), "日本語の文章です。");
+    }
+
+    #[test]
+    fn japanese_joins_lines_without_space() {
+        assert_eq!(normalize("これは\nペンです。"), "これはペンです。");
+    }
+
+    #[test]
+    fn japanese_keeps_space_next_to_latin() {
+        assert_eq!(normalize("日本語 English 日本語"), "日本語 English 日本語");
+    }
+
+    // --- Chinese ---
+
+    #[test]
+    fn chinese_removes_spaces_around_punctuation() {
+        assert_eq!(normalize("你好 ， 世界 ！"), "你好，世界！");
+    }
+
+    #[test]
+    fn chinese_keeps_break_after_sentence() {
+        assert_eq!(normalize("第一句。\n第二 句"), "第一句。\n第二句");
+    }
+
+    // --- Thai ---
+
+    #[test]
+    fn thai_keeps_spaces_between_phrases() {
+        assert_eq!(normalize("สวัสดีครับ ขอบคุณครับ"), "สวัสดีครับ ขอบคุณครับ");
+        assert_eq!(normalize("ภาษาไทย ง่าย"), "ภาษาไทย ง่าย");
+    }
+
+    #[test]
+    fn thai_rejoins_split_fragments() {
+        // A leading vowel cut off from its consonant.
+        assert_eq!(normalize("เ ด็ก"), "เด็ก");
+        // A vowel mark cut off from its consonant.
+        assert_eq!(normalize("สวัสด ี"), "สวัสดี");
+        // A single consonant; the space after the rejoined word stays.
+        assert_eq!(normalize("ครั บ ผม"), "ครับ ผม");
+    }
+
+    #[test]
+    fn thai_keeps_space_next_to_chinese() {
+        assert_eq!(normalize("中文 ภาษาไทย"), "中文 ภาษาไทย");
+    }
+
+    #[test]
+    fn thai_joins_lines_without_space() {
+        assert_eq!(normalize("ภาษา\nไทย"), "ภาษาไทย");
+    }
+
+    // --- Korean ---
+
+    #[test]
+    fn korean_keeps_spaces_between_words() {
+        assert_eq!(normalize("안녕 하세요"), "안녕 하세요");
+    }
+
+    #[test]
+    fn korean_joins_lines_with_space() {
+        assert_eq!(normalize("한국어\n문장입니다."), "한국어 문장입니다.");
+    }
+}
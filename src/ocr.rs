@@ -1,143 +1,726 @@
-use futures_util::stream::StreamExt;
-use rand::Rng;
-use std::collections::HashMap;
-use std::env;
+use crate::languages::{self, Language};
+use crate::normalize;
+use crate::script::Script;
+use crate::translation::is_single_word;
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
-use zbus::zvariant::{ObjectPath, Str, Value};
-use zbus::Connection;
-
-/// The main entry point for capturing and OCR'ing text.
-/// It detects the current desktop environment and calls the appropriate
-/// screen capture utility.
-pub async fn capture_and_ocr(lang: &str) -> Result<String, Box<dyn std::error::Error>> {
-    // 1. Detect the current desktop environment.
-    let de = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
-
-    // 2. Call the appropriate capture function, which returns a temporary file path.
-    // The `?` operator will propagate any errors, such as the user cancelling the capture.
-    let image_path = if de.to_uppercase().contains("KDE") {
-        capture_kde().await?
-    } else {
-        // Default to the Freedesktop portal method for GNOME, etc.
-        capture_portal().await?
-    };
-
-    // 3. Read the image data from the file.
-    let image_data = fs::read(&image_path)?;
-
-    // 4. Clean up the temporary screenshot file immediately after reading.
-    let _ = fs::remove_file(&image_path);
-
-    // 5. Perform OCR on the image data in memory.
-    let ocr_text = tesseract::Tesseract::new(None, Some(lang))?
-        .set_image_from_mem(&image_data)?
-        .get_text()?;
-
-    Ok(ocr_text)
-}
-
-/// Captures a screen region using KDE's Spectacle tool.
-/// This is a command-line approach that is often more reliable on KDE Plasma.
-async fn capture_kde() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    // Generate a random file name for the temporary screenshot.
-    let mut rng = rand::rng();
-    let temp_file_name: String = (0..12)
-        .map(|_| rng.sample(rand::distr::Alphanumeric) as char)
-        .collect();
-    let temp_path = env::temp_dir().join(format!("capture_{}.png", temp_file_name));
-    let temp_path_str = temp_path
-        .to_str()
-        .ok_or("Failed to create a temporary file path.")?;
-
-    // Execute Spectacle in region selection mode.
-    // -b: non-GUI, background mode
-    // -n: no notification
-    // -r: region mode
-    // -o: output file
-    let output = Command::new("spectacle")
-        .args(["-b", "-n", "-r", "-o", temp_path_str])
-        .output()?;
-
-    if !output.status.success() {
-        // This typically happens if the user presses 'Esc' to cancel the screenshot.
-        return Err("Screenshot cancelled by user.".into());
-    }
-
-    Ok(temp_path)
-}
-
-/// Captures a screen region using the Freedesktop Screenshot portal (DBus).
-/// This is the standard method for Wayland and works best on GNOME and other
-/// non-KDE environments.
-async fn capture_portal() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let connection = Connection::session().await?;
-
-    // Generate a unique token for the portal request.
-    const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz01234d56789";
-    let mut rng = rand::rng();
-    let token: String = (0..10)
-        .map(|_| {
-            let idx = rng.random_range(0..CHARSET.len());
-            CHARSET[idx] as char
+use std::io::{self, Read};
+use std::path::Path;
+use tesseract::PageSegMode;
+
+/// Resolution reported to Tesseract for in-memory frames, which carry no DPI information.
+const SOURCE_DPI: i32 = 300;
+
+/// Words below this confidence are still shown, but marked as uncertain in the UI.
+pub const UNCERTAIN_CONFIDENCE: f32 = 70.0;
+
+/// The script detection pass reads a copy of the capture with at most this many pixels.
+/// Larger captures are scaled down (to half size at most) and then cut off at the bottom.
+const PROBE_MAX_PIXELS: u32 = 150_000;
+const PROBE_MIN_SCALE: f32 = 0.5;
+/// A second script is recognized as well when it makes up at least this share of the letters.
+const SECOND_SCRIPT_SHARE: f32 = 0.25;
+/// Number of Tesseract instances `OcrEngine` keeps loaded.
+const MAX_INSTANCES: usize = 3;
+
+/// Captures at most this tall (before preprocessing, at a scale factor of 1)
+/// hold a single line of text.
+const SINGLE_LINE_MAX_HEIGHT: f32 = 60.0;
+/// A single-line capture less than this many times wider than tall is a single word.
+const SINGLE_WORD_MAX_RATIO: f32 = 3.0;
+/// A capture at least this many times taller than wide holds vertical text,
+/// if all of the languages can be written vertically.
+const VERTICAL_MIN_RATIO: f32 = 2.0;
+
+// --- Data Structures ---
+
+/// The languages to recognize with, resolved from `--ocr-lang`.
+#[derive(Debug, Clone)]
+pub struct OcrSettings {
+    pub languages: Vec<Language>,
+    /// Narrow `languages` down for each capture with a quick first pass that
+    /// finds out which scripts it contains (`--ocr-lang auto`).
+    pub detect_script: bool,
+    /// OCR words below this confidence are dropped. Without it, the default of
+    /// the languages that end up being used applies.
+    pub min_confidence: Option<f32>,
+    pub psm: Psm,
+    /// Physical pixels per logical pixel on the screen captures come from; the
+    /// size limits of `Psm::Auto` are scaled by it.
+    pub scale_factor: f32,
+    /// Clean up the recognized text before it is translated (see `normalize`).
+    pub normalize: bool,
+}
+
+/// How Tesseract splits the image into text (its page segmentation mode).
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Psm {
+    /// Pick word, line, block or vertical from the shape of the capture.
+    Auto,
+    /// Full page layout analysis with columns and paragraphs (Tesseract's own default).
+    Page,
+    /// A single uniform block of text.
+    Block,
+    /// A single line of text.
+    Line,
+    /// A single word.
+    Word,
+    /// A block of vertical text, as in Japanese or Chinese books and manga.
+    Vertical,
+    /// As much text as possible, in no particular order.
+    Sparse,
+}
+
+impl OcrSettings {
+    /// With one or two languages there is nothing worth narrowing down.
+    fn should_detect(&self) -> bool {
+        self.detect_script && self.languages.len() > 2
+    }
+
+    /// The page segmentation mode for a capture of this size (before
+    /// preprocessing), recognized with `languages`. `Auto` is resolved by the
+    /// aspect ratio: tall and narrow captures are vertical text when every
+    /// language is Chinese, Japanese or Korean, short ones a single line or
+    /// word, and anything else a block.
+    pub fn psm_for_capture(&self, width: u32, height: u32, languages: &[&Language]) -> Psm {
+        if self.psm != Psm::Auto {
+            return self.psm;
+        }
+
+        let width = width.max(1) as f32;
+        let height = height.max(1) as f32;
+        let written_vertically = !languages.is_empty()
+            && languages.iter().all(|language| {
+                matches!(
+                    language.script,
+                    Script::Japanese | Script::Han | Script::Hangul
+                )
+            });
+
+        if written_vertically && height / width >= VERTICAL_MIN_RATIO {
+            Psm::Vertical
+        } else if height <= SINGLE_LINE_MAX_HEIGHT * self.scale_factor.max(1.0) {
+            if width / height < SINGLE_WORD_MAX_RATIO {
+                Psm::Word
+            } else {
+                Psm::Line
+            }
+        } else {
+            Psm::Block
+        }
+    }
+}
+
+impl Psm {
+    fn to_tesseract(self) -> PageSegMode {
+        match self {
+            Psm::Auto | Psm::Page => PageSegMode::PsmAuto,
+            Psm::Block => PageSegMode::PsmSingleBlock,
+            Psm::Line => PageSegMode::PsmSingleLine,
+            Psm::Word => PageSegMode::PsmSingleWord,
+            Psm::Vertical => PageSegMode::PsmSingleBlockVertText,
+            Psm::Sparse => PageSegMode::PsmSparseText,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrWord {
+    pub text: String,
+    pub bbox: BoundingBox,
+    pub confidence: f32, // 0-100, as reported by Tesseract
+    /// How the word reads in `OcrResult::text` after `normalize_text`
+    /// (`trans-` → `trans`, `W0RLD` → `WORLD`).
+    #[serde(skip)]
+    pub normalized: Option<String>,
+}
+
+impl OcrWord {
+    pub fn is_uncertain(&self) -> bool {
+        self.confidence < UNCERTAIN_CONFIDENCE
+    }
+
+    /// The word as it appears in `OcrResult::text`.
+    pub fn text_in_result(&self) -> &str {
+        self.normalized.as_deref().unwrap_or(&self.text)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrLine {
+    pub bbox: BoundingBox,
+    pub words: Vec<OcrWord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrResult {
+    /// The recognized text after filtering, with one line per OCR line.
+    pub text: String,
+    pub lines: Vec<OcrLine>,
+    pub mean_confidence: f32,
+    pub script: Script,
+    /// Tesseract languages the text was recognized with, e.g. `["jpn"]`, or
+    /// `["jpn_vert"]` for vertical text.
+    pub languages: Vec<String>,
+}
+
+impl OcrResult {
+    /// Builds a result from Tesseract's TSV output, dropping words below
+    /// `min_confidence` and words made only of junk characters.
+    pub fn from_tsv(tsv: &str, min_confidence: f32) -> Self {
+        let all_lines = parse_tsv(tsv);
+
+        let mut lines = filter_lines(&all_lines, |word| {
+            word.confidence >= min_confidence && has_content(&word.text)
+        });
+        // If everything was below the threshold, keep the readable words
+        // (marked as uncertain) rather than showing nothing at all.
+        if lines.is_empty() {
+            lines = filter_lines(&all_lines, |word| has_content(&word.text));
+        }
+
+        let text = trim_single_word(
+            lines
+                .iter()
+                .map(|line| join_words(&line.words))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+
+        let words: Vec<&OcrWord> = lines.iter().flat_map(|line| &line.words).collect();
+        let mean_confidence = if words.is_empty() {
+            0.0
+        } else {
+            words.iter().map(|word| word.confidence).sum::<f32>() / words.len() as f32
+        };
+
+        Self {
+            script: Script::detect(&text),
+            text,
+            lines,
+            mean_confidence,
+            languages: Vec::new(),
+        }
+    }
+
+    /// Rejoins hyphenated words and wrapped lines and fixes common OCR
+    /// confusions in `text`. The words in `lines` stay as Tesseract read them,
+    /// with what became of each in `OcrWord::normalized`.
+    pub fn normalize_text(&mut self) {
+        let raw = std::mem::take(&mut self.text);
+        self.text = trim_single_word(normalize::normalize(&raw));
+        let spans = normalize::char_spans(&raw, &self.text);
+
+        let mut cursor = 0;
+        for word in self.lines.iter_mut().flat_map(|line| &mut line.words) {
+            let Some(offset) = raw[cursor..].find(&word.text) else {
+                continue;
+            };
+            let start = raw[..cursor + offset].chars().count();
+            cursor += offset + word.text.len();
+            let mapped: Vec<_> = spans[start..start + word.text.chars().count()]
+                .iter()
+                .flatten()
+                .collect();
+            word.normalized = Some(match (mapped.first(), mapped.last()) {
+                (Some(first), Some(last)) => self.text[first.start..last.end].to_string(),
+                _ => String::new(),
+            });
+        }
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = &OcrWord> {
+        self.lines.iter().flat_map(|line| &line.words)
+    }
+
+    pub fn has_uncertain_words(&self) -> bool {
+        self.words().any(OcrWord::is_uncertain)
+    }
+}
+
+// --- Image Input ---
+
+/// Reads an existing image instead of taking a screenshot.
+/// A path of `-` reads the image bytes from stdin.
+pub fn read_image(path: &Path) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if path.as_os_str() == "-" {
+        let mut image_data = Vec::new();
+        io::stdin().read_to_end(&mut image_data)?;
+        return Ok(image_data);
+    }
+
+    fs::read(path).map_err(|e| format!("Failed to read image '{}': {}", path.display(), e).into())
+}
+
+// --- Recognition ---
+
+/// Performs OCR on a decoded (and usually preprocessed) image. `capture_size`
+/// is the size before preprocessing, which the page segmentation mode is chosen from.
+pub fn ocr_image(
+    image: &DynamicImage,
+    settings: &OcrSettings,
+    capture_size: (u32, u32),
+) -> Result<OcrResult, Box<dyn std::error::Error>> {
+    OcrEngine::new().recognize(image, settings, capture_size)
+}
+
+/// Keeps Tesseract instances alive between recognitions, so the language
+/// data is only loaded once. Used by the daemon; one-shot runs use `ocr_image`.
+#[derive(Default)]
+pub struct OcrEngine {
+    /// Loaded instances by language string, least recently used first.
+    instances: Vec<(String, tesseract::Tesseract)>,
+}
+
+impl OcrEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the language data ahead of the first recognition: the languages of
+    /// the script detection pass if there is one, or else all of them.
+    pub fn warm_up(&mut self, settings: &OcrSettings) -> Result<(), Box<dyn std::error::Error>> {
+        let languages = if settings.should_detect() {
+            probe_languages(&settings.languages)
+        } else {
+            settings.languages.iter().collect()
+        };
+        let lang = languages::tesseract_string(&languages, false);
+        let tesseract = self.take_instance(&lang)?;
+        self.keep_instance(lang, tesseract);
+        Ok(())
+    }
+
+    pub fn recognize(
+        &mut self,
+        image: &DynamicImage,
+        settings: &OcrSettings,
+        capture_size: (u32, u32),
+    ) -> Result<OcrResult, Box<dyn std::error::Error>> {
+        let (width, height) = capture_size;
+        let languages = if settings.should_detect() {
+            // Vertical text is only assumed once the scripts are known.
+            let probe_psm = settings.psm_for_capture(width, height, &[]);
+            self.detect_languages(image, &settings.languages, probe_psm)?
+        } else {
+            settings.languages.iter().collect()
+        };
+        let psm = settings.psm_for_capture(width, height, &languages);
+        let vertical = psm == Psm::Vertical;
+        let min_confidence = settings
+            .min_confidence
+            .unwrap_or_else(|| languages::min_confidence(&languages));
+
+        let mut result = self.recognize_with(
+            image,
+            &languages::tesseract_string(&languages, vertical),
+            psm,
+            min_confidence,
+        )?;
+        result.languages = languages
+            .iter()
+            .map(|language| language.data_name(vertical).to_string())
+            .collect();
+        if settings.normalize {
+            result.normalize_text();
+        }
+        Ok(result)
+    }
+
+    /// The first pass: reads a small copy of the image with one language per
+    /// script and keeps the candidates for the scripts it finds. When it finds
+    /// none it knows, all candidates are kept.
+    fn detect_languages<'a>(
+        &mut self,
+        image: &DynamicImage,
+        candidates: &'a [Language],
+        psm: Psm,
+    ) -> Result<Vec<&'a Language>, Box<dyn std::error::Error>> {
+        let probe = probe_languages(candidates);
+        let sample = probe_sample(image);
+        let text = self
+            .recognize_with(
+                &sample,
+                &languages::tesseract_string(&probe, psm == Psm::Vertical),
+                psm,
+                languages::DEFAULT_MIN_CONFIDENCE,
+            )?
+            .text;
+
+        let mut chosen: Vec<&Language> = Vec::new();
+        for script in detected_scripts(&text) {
+            let mut matching: Vec<&Language> = candidates
+                .iter()
+                .filter(|language| language.script == script)
+                .collect();
+            // Kanji without kana look like Chinese and the other way around;
+            // either model reads them when the other is not installed.
+            if matching.is_empty() && matches!(script, Script::Han | Script::Japanese) {
+                matching = candidates
+                    .iter()
+                    .filter(|language| matches!(language.script, Script::Han | Script::Japanese))
+                    .collect();
+            }
+            chosen.extend(matching);
+        }
+
+        if chosen.is_empty() {
+            return Ok(candidates.iter().collect());
+        }
+        Ok(chosen)
+    }
+
+    fn recognize_with(
+        &mut self,
+        image: &DynamicImage,
+        lang: &str,
+        psm: Psm,
+        min_confidence: f32,
+    ) -> Result<OcrResult, Box<dyn std::error::Error>> {
+        // Grayscale images are handed over as-is; everything else as packed RGB.
+        let (frame, bytes_per_pixel): (Cow<[u8]>, i32) = match image {
+            DynamicImage::ImageLuma8(gray) => (Cow::Borrowed(gray.as_raw()), 1),
+            other => (Cow::Owned(other.to_rgb8().into_raw()), 3),
+        };
+        let width = image.width() as i32;
+        let height = image.height() as i32;
+
+        // On error the instance is dropped and the next call starts a fresh one.
+        let mut tesseract = self.take_instance(lang)?;
+        tesseract.set_page_seg_mode(psm.to_tesseract());
+        let mut tesseract = tesseract
+            .set_frame(
+                &frame,
+                width,
+                height,
+                bytes_per_pixel,
+                width * bytes_per_pixel,
+            )?
+            .set_source_resolution(SOURCE_DPI)
+            .recognize()?;
+        let tsv = tesseract.get_tsv_text(0)?;
+        self.keep_instance(lang.to_string(), tesseract);
+
+        Ok(OcrResult::from_tsv(&tsv, min_confidence))
+    }
+
+    /// Takes the instance loaded for `lang` out of the cache, or loads a new one.
+    fn take_instance(
+        &mut self,
+        lang: &str,
+    ) -> Result<tesseract::Tesseract, Box<dyn std::error::Error>> {
+        match self.instances.iter().position(|(loaded, _)| loaded == lang) {
+            Some(index) => Ok(self.instances.remove(index).1),
+            None => Ok(tesseract::Tesseract::new(None, Some(lang))?),
+        }
+    }
+
+    /// Puts an instance back as the most recently used one.
+    fn keep_instance(&mut self, lang: String, tesseract: tesseract::Tesseract) {
+        if self.instances.len() >= MAX_INSTANCES {
+            self.instances.remove(0);
+        }
+        self.instances.push((lang, tesseract));
+    }
+}
+
+// --- Script Detection ---
+
+/// The languages of the first pass: the first candidate of each script, and
+/// every language whose script is unknown.
+fn probe_languages(candidates: &[Language]) -> Vec<&Language> {
+    let mut probe: Vec<&Language> = Vec::new();
+    for language in candidates {
+        if language.script == Script::Unknown
+            || !probe.iter().any(|other| other.script == language.script)
+        {
+            probe.push(language);
+        }
+    }
+    probe
+}
+
+/// A copy of the image small enough for a quick first pass.
+fn probe_sample(image: &DynamicImage) -> Cow<'_, DynamicImage> {
+    let pixels = image.width() * image.height();
+    if pixels <= PROBE_MAX_PIXELS {
+        return Cow::Borrowed(image);
+    }
+
+    // Scaling down further would make small text unreadable; the rest is cut
+    // off at the bottom instead, since selections usually start at the text.
+    let scale = (PROBE_MAX_PIXELS as f32 / pixels as f32)
+        .sqrt()
+        .max(PROBE_MIN_SCALE);
+    let width = ((image.width() as f32 * scale) as u32).max(1);
+    let height = ((image.height() as f32 * scale) as u32).max(1);
+    let sample = image.resize_exact(width, height, FilterType::Triangle);
+    let max_height = (PROBE_MAX_PIXELS / width).clamp(1, height);
+    Cow::Owned(sample.crop_imm(0, 0, width, max_height))
+}
+
+/// The scripts worth recognizing: the most frequent one, and the second one
+/// if it is common enough (e.g. English words in Japanese text).
+fn detected_scripts(text: &str) -> Vec<Script> {
+    let ranked = Script::ranked(text);
+    let total: usize = ranked.iter().map(|(_, count)| count).sum();
+    ranked
+        .into_iter()
+        .enumerate()
+        .filter(|(rank, (_, count))| {
+            *rank == 0 || (*rank == 1 && *count as f32 >= total as f32 * SECOND_SCRIPT_SHARE)
         })
-        .collect();
-    let sender = connection
-        .unique_name()
-        .unwrap()
-        .trim_start_matches(':')
-        .replace('.', "_");
-    let handle_str = format!("/org/freedesktop/portal/desktop/request/{sender}/{token}");
-    let handle = ObjectPath::try_from(handle_str)?;
-    let mut options: HashMap<&str, Value> = HashMap::new();
-    options.insert("handle_token", Str::from(token).into());
-    options.insert("interactive", true.into());
-
-    let proxy = zbus::Proxy::new(
-        &connection,
-        "org.freedesktop.portal.Desktop",
-        "/org/freedesktop/portal/desktop",
-        "org.freedesktop.portal.Screenshot",
-    )
-    .await?;
-
-    // Request the screenshot.
-    let _ = proxy.call_method("Screenshot", &("", options)).await?;
-
-    // Wait for the portal to respond with the URI of the saved file.
-    let request_proxy = zbus::Proxy::new(
-        &connection,
-        "org.freedesktop.portal.Desktop",
-        handle,
-        "org.freedesktop.portal.Request",
-    )
-    .await?;
-    let mut signal_stream = request_proxy.receive_signal("Response").await?;
-    let response_signal = signal_stream
-        .next()
-        .await
-        .ok_or("Portal did not send a response.")?;
-    let body = response_signal.body();
-    let (response_code, results): (u32, HashMap<String, Value>) = body.deserialize()?;
-
-    if response_code != 0 {
-        return Err("Portal request failed or was cancelled by user.".into());
-    }
-
-    // Extract the file path from the response URI.
-    let uri_value = results
-        .get("uri")
-        .ok_or("Portal response did not contain a URI.")?;
-
-    // Bind the Str to a variable so it lives long enough
-    let uri_str_obj = uri_value.downcast_ref::<Str>()?;
-    let uri_str = uri_str_obj.as_str();
-
-    let path_str = uri_str
-        .strip_prefix("file://")
-        .ok_or("URI was not a file URI.")?;
-    let decoded_path = urlencoding::decode(path_str)?.into_owned();
-
-    Ok(PathBuf::from(decoded_path))
+        .map(|(_, (script, _))| script)
+        .collect()
+}
+
+// --- TSV Parsing ---
+
+/// Parses Tesseract's TSV output into lines of words.
+/// Columns: level, page_num, block_num, par_num, line_num, word_num,
+/// left, top, width, height, conf, text.
+fn parse_tsv(tsv: &str) -> Vec<OcrLine> {
+    const WORD_LEVEL: &str = "5";
+
+    let mut lines: Vec<OcrLine> = Vec::new();
+    let mut current_key: Option<(&str, &str, &str)> = None;
+
+    for row in tsv.lines() {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != WORD_LEVEL {
+            continue;
+        }
+
+        let text = columns[11].trim();
+        let confidence: f32 = columns[10].parse().unwrap_or(-1.0);
+        if text.is_empty() || confidence < 0.0 {
+            continue;
+        }
+
+        let number = |i: usize| columns[i].parse::<i32>().unwrap_or(0);
+        let word = OcrWord {
+            text: text.to_string(),
+            bbox: BoundingBox {
+                left: number(6),
+                top: number(7),
+                width: number(8),
+                height: number(9),
+            },
+            confidence,
+            normalized: None,
+        };
+
+        // Words sharing block, paragraph and line numbers belong to the same line.
+        let key = (columns[2], columns[3], columns[4]);
+        match lines.last_mut() {
+            Some(line) if current_key == Some(key) => line.words.push(word),
+            _ => {
+                lines.push(OcrLine {
+                    bbox: word.bbox,
+                    words: vec![word],
+                });
+                current_key = Some(key);
+            }
+        }
+    }
+
+    for line in &mut lines {
+        line.bbox = union_bbox(&line.words);
+    }
+    lines
+}
+
+fn filter_lines(lines: &[OcrLine], keep: impl Fn(&OcrWord) -> bool) -> Vec<OcrLine> {
+    lines
+        .iter()
+        .filter_map(|line| {
+            let words: Vec<OcrWord> = line.words.iter().filter(|w| keep(w)).cloned().collect();
+            if words.is_empty() {
+                None
+            } else {
+                Some(OcrLine {
+                    bbox: union_bbox(&words),
+                    words,
+                })
+            }
+        })
+        .collect()
+}
+
+fn union_bbox(words: &[OcrWord]) -> BoundingBox {
+    let left = words.iter().map(|w| w.bbox.left).min().unwrap_or(0);
+    let top = words.iter().map(|w| w.bbox.top).min().unwrap_or(0);
+    let right = words
+        .iter()
+        .map(|w| w.bbox.left + w.bbox.width)
+        .max()
+        .unwrap_or(0);
+    let bottom = words
+        .iter()
+        .map(|w| w.bbox.top + w.bbox.height)
+        .max()
+        .unwrap_or(0);
+    BoundingBox {
+        left,
+        top,
+        width: right - left,
+        height: bottom - top,
+    }
+}
+
+/// A word is junk if it has no letters or digits at all (e.g. `|`, `~~`, `»`).
+fn has_content(text: &str) -> bool {
+    text.chars().any(char::is_alphanumeric)
+}
+
+/// For single words, trims any special characters from the start and end.
+fn trim_single_word(text: String) -> String {
+    if is_single_word(&text) {
+        text.trim_matches(|c: char| !c.is_alphanumeric())
+            .to_string()
+    } else {
+        text
+    }
+}
+
+/// Joins words with spaces, except between two words of a script that is
+/// written without spaces (Chinese, Japanese, Thai).
+fn join_words(words: &[OcrWord]) -> String {
+    let mut text = String::new();
+    let mut previous_spaceless = false;
+    for word in words {
+        let spaceless = word
+            .text
+            .chars()
+            .next()
+            .and_then(Script::of_char)
+            .is_some_and(Script::is_spaceless);
+        let glued = previous_spaceless && spaceless;
+        if !text.is_empty() && !glued {
+            text.push(' ');
+        }
+        text.push_str(&word.text);
+        previous_spaceless = word
+            .text
+            .chars()
+            .last()
+            .and_then(Script::of_char)
+            .is_some_and(Script::is_spaceless);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(tesseract: &'static str, script: Script) -> Language {
+        Language {
+            tesseract: Cow::Borrowed(tesseract),
+            code: None,
+            aliases: &[],
+            name: Cow::Borrowed(tesseract),
+            script,
+            font: None,
+            min_confidence: languages::DEFAULT_MIN_CONFIDENCE,
+            vertical: None,
+        }
+    }
+
+    fn settings(scale_factor: f32) -> OcrSettings {
+        OcrSettings {
+            languages: Vec::new(),
+            detect_script: false,
+            min_confidence: None,
+            psm: Psm::Auto,
+            scale_factor,
+            normalize: true,
+        }
+    }
+
+    #[test]
+    fn tall_english_capture_is_a_block() {
+        let eng = language("eng", Script::Latin);
+        let jpn = language("jpn", Script::Japanese);
+        assert_eq!(settings(1.0).psm_for_capture(80, 400, &[&eng]), Psm::Block);
+        // Candidates that only might be CJK do not make it vertical.
+        assert_eq!(
+            settings(1.0).psm_for_capture(80, 400, &[&eng, &jpn]),
+            Psm::Block
+        );
+        assert_eq!(settings(1.0).psm_for_capture(80, 400, &[]), Psm::Block);
+    }
+
+    #[test]
+    fn tall_cjk_capture_is_vertical() {
+        let jpn = language("jpn", Script::Japanese);
+        let chi = language("chi_sim", Script::Han);
+        assert_eq!(
+            settings(1.0).psm_for_capture(80, 400, &[&jpn, &chi]),
+            Psm::Vertical
+        );
+        // Not tall enough.
+        assert_eq!(settings(1.0).psm_for_capture(300, 400, &[&jpn]), Psm::Block);
+    }
+
+    #[test]
+    fn short_capture_is_a_line_or_word() {
+        let eng = language("eng", Script::Latin);
+        assert_eq!(settings(1.0).psm_for_capture(400, 40, &[&eng]), Psm::Line);
+        assert_eq!(settings(1.0).psm_for_capture(100, 40, &[&eng]), Psm::Word);
+        assert_eq!(settings(1.0).psm_for_capture(800, 100, &[&eng]), Psm::Block);
+    }
+
+    #[test]
+    fn single_line_height_scales_with_the_screen() {
+        let eng = language("eng", Script::Latin);
+        assert_eq!(settings(2.0).psm_for_capture(800, 100, &[&eng]), Psm::Line);
+        assert_eq!(settings(2.0).psm_for_capture(200, 100, &[&eng]), Psm::Word);
+        assert_eq!(settings(2.0).psm_for_capture(800, 140, &[&eng]), Psm::Block);
+    }
+
+    #[test]
+    fn normalized_words_point_into_the_text() {
+        let row = |line: u32, word: u32, conf: f32, text: &str| {
+            format!(
+                "5\t1\t1\t1\t{}\t{}\t0\t0\t10\t10\t{}\t{}",
+                line, word, conf, text
+            )
+        };
+        let tsv = [
+            "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext".to_string(),
+            row(1, 1, 95.0, "a"),
+            row(1, 2, 40.0, "trans-"),
+            row(2, 1, 50.0, "lati0n"),
+        ]
+        .join("\n");
+        let mut result = OcrResult::from_tsv(&tsv, 0.0);
+        assert_eq!(result.text, "a trans-\nlati0n");
+
+        result.normalize_text();
+        assert_eq!(result.text, "a translation");
+        let shown: Vec<&str> = result.words().map(OcrWord::text_in_result).collect();
+        assert_eq!(shown, ["a", "trans", "lation"]);
+    }
+
+    #[test]
+    fn explicit_psm_is_kept() {
+        let eng = language("eng", Script::Latin);
+        let mut settings = settings(1.0);
+        settings.psm = Psm::Sparse;
+        assert_eq!(settings.psm_for_capture(80, 400, &[&eng]), Psm::Sparse);
+    }
 }
@@ -0,0 +1,265 @@
+use crate::history::{self, HistoryEntry};
+use crate::ocr::OcrResult;
+use crate::translation::{CombinedTranslationData, TranslationItem};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Version of the machine-readable output schema.
+/// Bump this whenever a field is renamed or removed, so consumers can detect the change.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq)]
+pub enum OutputFormat {
+    /// Human-readable text, following the same sections as the UI.
+    Text,
+    /// One pretty-printed JSON document.
+    Json,
+    /// One compact JSON record per line.
+    Ndjson,
+}
+
+/// The envelope every JSON/NDJSON record is wrapped in.
+#[derive(Serialize)]
+struct OutputRecord<'a> {
+    schema_version: u32,
+    kind: &'static str,
+    result: &'a CombinedTranslationData,
+    /// Word boxes and confidences, when the text came from OCR.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ocr: Option<&'a OcrResult>,
+}
+
+impl<'a> OutputRecord<'a> {
+    fn translation(result: &'a CombinedTranslationData, ocr: Option<&'a OcrResult>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            kind: "translation",
+            result,
+            ocr,
+        }
+    }
+}
+
+/// Prints a translation result to stdout in the requested format.
+pub fn print_translation(
+    data: &CombinedTranslationData,
+    ocr: Option<&OcrResult>,
+    format: OutputFormat,
+) -> Result<(), serde_json::Error> {
+    match format {
+        OutputFormat::Text => print_text(data),
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&OutputRecord::translation(data, ocr))?
+            );
+        }
+        OutputFormat::Ndjson => {
+            println!(
+                "{}",
+                serde_json::to_string(&OutputRecord::translation(data, ocr))?
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The envelope for `history` listings.
+#[derive(Serialize)]
+struct HistoryRecord<'a> {
+    schema_version: u32,
+    kind: &'static str,
+    entries: &'a [HistoryEntry],
+}
+
+/// Prints past lookups, newest first. NDJSON prints one record per lookup.
+pub fn print_history(
+    entries: &[HistoryEntry],
+    format: OutputFormat,
+) -> Result<(), serde_json::Error> {
+    let record = |entries| HistoryRecord {
+        schema_version: SCHEMA_VERSION,
+        kind: "history",
+        entries,
+    };
+
+    match format {
+        OutputFormat::Text => {
+            for entry in entries {
+                println!(
+                    "{}  {} -> {}  {}  {}",
+                    history::format_timestamp(entry.timestamp),
+                    entry.source_lang,
+                    entry.target_lang,
+                    entry.text,
+                    entry.data.summary()
+                );
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&record(entries))?),
+        OutputFormat::Ndjson => {
+            for entry in entries {
+                println!(
+                    "{}",
+                    serde_json::to_string(&record(std::slice::from_ref(entry)))?
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the translation result as plain text, following the same sections as the UI.
+fn print_text(data: &CombinedTranslationData) {
+    println!("{}", data.search_word);
+
+    if !data.google_translation.is_empty() {
+        println!();
+        println!("Google ({}):", data.target_lang);
+        println!("  • {}", data.google_translation);
+    }
+
+    for section in &data.sections {
+        println!();
+        println!("{} ({}):", section.provider, data.target_lang);
+        if let Some(translation) = &section.translation {
+            println!("  • {}", translation);
+        }
+        for item in &section.entries {
+            println!(
+                "  • {} [{}] {} ({})",
+                item.word, item.pos, item.translation, item.dictionary
+            );
+        }
+    }
+
+    if let Some(longdo) = &data.longdo_data {
+        if !longdo.translations.is_empty() {
+            println!();
+            println!("Longdo Dict:");
+            for item in &longdo.translations {
+                println!(
+                    "  • {} [{}] {} ({})",
+                    item.word, item.pos, item.translation, item.dictionary
+                );
+            }
+        }
+
+        if !longdo.examples.is_empty() {
+            println!();
+            println!("Example Sentences (Longdo):");
+            for ex in &longdo.examples {
+                println!("  • {}: {}", data.source_lang, ex.en);
+                println!("    -> {}: {}", data.target_lang, ex.th);
+            }
+        }
+    }
+
+    for error in &data.errors {
+        eprintln!("{} failed: {}", error.provider, error.message);
+    }
+}
+
+/// Formats the translation result as Markdown, for pasting into chat or notes.
+pub fn markdown(data: &CombinedTranslationData) -> String {
+    let mut md = format!("**{}**\n", data.search_word.trim());
+    let target = data.target_lang.to_uppercase();
+    let item_line = |item: &TranslationItem| {
+        format!(
+            "- **{}** *[{}]* {} ({})\n",
+            item.word, item.pos, item.translation, item.dictionary
+        )
+    };
+
+    if !data.google_translation.is_empty() {
+        md += &format!("\n### Google ({})\n\n{}\n", target, data.google_translation);
+    }
+
+    for section in &data.sections {
+        md += &format!("\n### {} ({})\n\n", section.provider, target);
+        if let Some(translation) = &section.translation {
+            md += &format!("- {}\n", translation);
+        }
+        for item in &section.entries {
+            md += &item_line(item);
+        }
+    }
+
+    if let Some(longdo) = &data.longdo_data {
+        if !longdo.translations.is_empty() {
+            md += "\n### Longdo Dict\n\n";
+            for item in &longdo.translations {
+                md += &item_line(item);
+            }
+        }
+
+        if !longdo.examples.is_empty() {
+            md += "\n### Example Sentences (Longdo)\n\n";
+            for ex in &longdo.examples {
+                md += &format!(
+                    "- {}: {}\n  - {}: {}\n",
+                    data.source_lang.to_uppercase(),
+                    ex.en,
+                    target,
+                    ex.th
+                );
+            }
+        }
+    }
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn translation_envelope() {
+        let data = CombinedTranslationData::sample("hello", "สวัสดี");
+        let record = serde_json::to_value(OutputRecord::translation(&data, None)).unwrap();
+        assert_eq!(record["schema_version"], SCHEMA_VERSION);
+        assert_eq!(record["kind"], "translation");
+        assert_eq!(record["result"]["google_translation"], "สวัสดี");
+        assert!(record.get("ocr").is_none());
+    }
+
+    #[test]
+    fn translation_envelope_with_ocr() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                   5\t1\t1\t1\t1\t1\t4\t5\t30\t12\t91.5\tHELL0";
+        let mut ocr = OcrResult::from_tsv(tsv, 0.0);
+        ocr.normalize_text();
+        let data = CombinedTranslationData::sample("HELLO", "สวัสดี");
+
+        let record = serde_json::to_value(OutputRecord::translation(&data, Some(&ocr))).unwrap();
+        assert_eq!(record["ocr"]["text"], "HELLO");
+        let word = &record["ocr"]["lines"][0]["words"][0];
+        assert_eq!(word["text"], "HELL0");
+        assert_eq!(word["confidence"], 91.5);
+        assert_eq!(word["bbox"]["width"], 30);
+        // Only kept for the window; not part of the schema.
+        assert!(word.get("normalized").is_none());
+    }
+
+    #[test]
+    fn history_envelope() {
+        let entries = [HistoryEntry {
+            timestamp: 1_700_000_000,
+            text: "hello".to_string(),
+            source_lang: "EN".to_string(),
+            target_lang: "th".to_string(),
+            data: CombinedTranslationData::sample("hello", "สวัสดี"),
+        }];
+        let record = serde_json::to_value(HistoryRecord {
+            schema_version: SCHEMA_VERSION,
+            kind: "history",
+            entries: &entries,
+        })
+        .unwrap();
+        assert_eq!(record["kind"], "history");
+        assert_eq!(record["schema_version"], SCHEMA_VERSION);
+        assert!(matches!(&record["entries"], Value::Array(entries) if entries.len() == 1));
+        assert_eq!(record["entries"][0]["timestamp"], 1_700_000_000);
+    }
+}
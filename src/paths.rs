@@ -0,0 +1,43 @@
+use std::io;
+use std::path::PathBuf;
+
+const APP_DIR_NAME: &str = "floating-dictionary-linux";
+
+/// Returns the directory for the application's data files
+/// (e.g. `~/.local/share/floating-dictionary-linux` on Linux).
+pub fn app_data_dir() -> io::Result<PathBuf> {
+    // Find a suitable directory to store our data files (e.g., ~/.local/share on Linux).
+    let data_dir = dirs::data_dir().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "Could not find a valid data directory.",
+        )
+    })?;
+    Ok(data_dir.join(APP_DIR_NAME))
+}
+
+/// Returns the directory for the configuration file
+/// (e.g. `~/.config/floating-dictionary-linux` on Linux).
+pub fn app_config_dir() -> io::Result<PathBuf> {
+    let config_dir = dirs::config_dir().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "Could not find a valid config directory.",
+        )
+    })?;
+    Ok(config_dir.join(APP_DIR_NAME))
+}
+
+/// A fresh, empty directory under the system temp directory for one test.
+#[cfg(test)]
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "{}-test-{}-{}",
+        APP_DIR_NAME,
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("create test directory");
+    dir
+}
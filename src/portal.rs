@@ -0,0 +1,157 @@
+use futures_util::stream::StreamExt;
+use rand::Rng;
+use std::collections::HashMap;
+use zbus::proxy::SignalStream;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Str, Value};
+use zbus::Connection;
+
+const DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+
+type PortalResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+// --- Requests ---
+
+/// A pending portal request. Portal methods return immediately and deliver
+/// their result later through the `Response` signal of a request object whose
+/// path is derived from our bus name and a `handle_token` we pick.
+pub struct Request {
+    token: String,
+    responses: SignalStream<'static>,
+}
+
+impl Request {
+    /// Subscribes to the response before the method is called, so it cannot be missed.
+    pub async fn new(connection: &Connection) -> PortalResult<Self> {
+        let token = new_token();
+        let sender = connection
+            .unique_name()
+            .ok_or("The D-Bus connection has no unique name.")?
+            .trim_start_matches(':')
+            .replace('.', "_");
+        let handle = ObjectPath::try_from(format!("{OBJECT_PATH}/request/{sender}/{token}"))?;
+
+        let proxy = zbus::Proxy::new(
+            connection,
+            DESTINATION,
+            handle,
+            "org.freedesktop.portal.Request",
+        )
+        .await?;
+        let responses = proxy.receive_signal("Response").await?;
+        Ok(Self { token, responses })
+    }
+
+    /// The value to pass as the `handle_token` option.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Waits for the portal to answer and returns its results.
+    pub async fn response(mut self) -> PortalResult<HashMap<String, OwnedValue>> {
+        let response_signal = self
+            .responses
+            .next()
+            .await
+            .ok_or("Portal did not send a response.")?;
+        let (response_code, results): (u32, HashMap<String, OwnedValue>) =
+            response_signal.body().deserialize()?;
+
+        if response_code != 0 {
+            return Err("Portal request failed or was cancelled by user.".into());
+        }
+        Ok(results)
+    }
+}
+
+/// Opens a proxy for one of the portal interfaces, e.g. `org.freedesktop.portal.Screenshot`.
+pub async fn proxy(
+    connection: &Connection,
+    interface: &'static str,
+) -> PortalResult<zbus::Proxy<'static>> {
+    Ok(zbus::Proxy::new(connection, DESTINATION, OBJECT_PATH, interface).await?)
+}
+
+// --- Global Shortcuts ---
+
+const SHORTCUT_ID: &str = "capture";
+
+/// A shortcut registered through the GlobalShortcuts portal. It stays bound
+/// for as long as this value (and so its D-Bus connection) is alive.
+pub struct GlobalShortcut {
+    _connection: Connection,
+    session: OwnedObjectPath,
+    activations: SignalStream<'static>,
+}
+
+impl GlobalShortcut {
+    /// Asks the desktop to bind `trigger` (e.g. `CTRL+ALT+D`) to capture and translate.
+    /// The desktop may show a dialog and let the user pick a different key.
+    pub async fn register(trigger: &str) -> PortalResult<Self> {
+        let connection = Connection::session().await?;
+        let proxy = proxy(&connection, "org.freedesktop.portal.GlobalShortcuts").await?;
+
+        // A session groups our shortcuts; it is closed when the connection goes away.
+        let request = Request::new(&connection).await?;
+        let mut options: HashMap<&str, Value> = HashMap::new();
+        options.insert("handle_token", Str::from(request.token()).into());
+        options.insert("session_handle_token", Str::from(new_token()).into());
+        proxy.call_method("CreateSession", &(options,)).await?;
+        let results = request.response().await?;
+        let session = match results.get("session_handle").map(|value| &**value) {
+            Some(Value::Str(handle)) => OwnedObjectPath::try_from(handle.as_str())?,
+            Some(Value::ObjectPath(handle)) => handle.clone().into(),
+            _ => return Err("Portal response did not contain a session handle.".into()),
+        };
+
+        let activations = proxy.receive_signal("Activated").await?;
+
+        let request = Request::new(&connection).await?;
+        let mut shortcut: HashMap<&str, Value> = HashMap::new();
+        shortcut.insert("description", "Capture and translate".into());
+        shortcut.insert("preferred_trigger", trigger.into());
+        let mut options: HashMap<&str, Value> = HashMap::new();
+        options.insert("handle_token", Str::from(request.token()).into());
+        proxy
+            .call_method(
+                "BindShortcuts",
+                &(&session, vec![(SHORTCUT_ID, shortcut)], "", options),
+            )
+            .await?;
+        request.response().await?;
+
+        Ok(Self {
+            _connection: connection,
+            session,
+            activations,
+        })
+    }
+
+    /// Waits until the shortcut is pressed. Returns `false` once the portal goes away.
+    pub async fn activated(&mut self) -> bool {
+        while let Some(signal) = self.activations.next().await {
+            let Ok((session, id, _timestamp, _options)) =
+                signal
+                    .body()
+                    .deserialize::<(OwnedObjectPath, String, u64, HashMap<String, OwnedValue>)>()
+            else {
+                continue;
+            };
+            if session == self.session && id == SHORTCUT_ID {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// --- Helper Functions ---
+
+/// A random token for request and session handles (only `[A-Za-z0-9_]` is allowed).
+fn new_token() -> String {
+    let mut rng = rand::rng();
+    let suffix: String = (0..10)
+        .map(|_| rng.sample(rand::distr::Alphanumeric) as char)
+        .collect();
+    format!("floating_dictionary_{}", suffix)
+}
@@ -0,0 +1,335 @@
+use clap::ValueEnum;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The steps used when `--preprocess` is not given.
+pub const DEFAULT_STEPS: &str = "upscale,grayscale,invert,pad";
+
+/// Captures shorter than this are scaled up, so small UI text reaches a size Tesseract reads well.
+const MIN_OCR_HEIGHT: u32 = 300;
+const MAX_UPSCALE: u32 = 4;
+/// Blank border added around the text; Tesseract struggles with glyphs touching the edge.
+const PADDING: u32 = 10;
+/// Window size and offset for the adaptive threshold.
+const ADAPTIVE_WINDOW: u32 = 31;
+const ADAPTIVE_OFFSET: i32 = 10;
+
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreprocessStep {
+    /// Pass the capture to Tesseract unchanged.
+    None,
+    /// Scale small captures up to a readable size.
+    Upscale,
+    /// Convert to grayscale.
+    Grayscale,
+    /// Invert the image when the background is dark (e.g. terminals, dark themes).
+    Invert,
+    /// Binarize with a single global threshold (Otsu's method).
+    Otsu,
+    /// Binarize with a local mean threshold; better for uneven backgrounds.
+    Adaptive,
+    /// Add a blank border around the image.
+    Pad,
+}
+
+/// Preprocessing settings for a single OCR run.
+#[derive(Clone, Debug)]
+pub struct PreprocessOptions {
+    pub steps: Vec<PreprocessStep>,
+    /// When set, the processed image is written here as a PNG for debugging.
+    pub debug_path: Option<PathBuf>,
+}
+
+impl PreprocessOptions {
+    fn has(&self, step: PreprocessStep) -> bool {
+        self.steps.contains(&step)
+    }
+}
+
+/// Decodes an encoded image (PNG, JPEG, BMP, PPM, ...) into memory.
+pub fn decode(image_data: &[u8]) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    if image_data.is_empty() {
+        return Err("Image is empty.".into());
+    }
+    Ok(image::load_from_memory(image_data)?)
+}
+
+/// Runs the enabled preprocessing steps on an image.
+/// The steps always run in the same order, regardless of how they were listed:
+/// upscale, grayscale, invert, threshold, pad.
+pub fn preprocess(
+    image: DynamicImage,
+    options: &PreprocessOptions,
+) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    if options.has(PreprocessStep::None) {
+        return Ok(image);
+    }
+
+    let mut image = image;
+
+    if options.has(PreprocessStep::Upscale) {
+        image = upscale(image);
+    }
+
+    let binarize = options.has(PreprocessStep::Otsu) || options.has(PreprocessStep::Adaptive);
+    if options.has(PreprocessStep::Grayscale) || binarize {
+        image = DynamicImage::ImageLuma8(image.to_luma8());
+    }
+
+    if options.has(PreprocessStep::Invert) && has_dark_background(&image.to_luma8()) {
+        image.invert();
+    }
+
+    if binarize {
+        let gray = image.to_luma8();
+        image = DynamicImage::ImageLuma8(if options.has(PreprocessStep::Adaptive) {
+            adaptive_threshold(&gray)
+        } else {
+            otsu_threshold(&gray)
+        });
+    }
+
+    if options.has(PreprocessStep::Pad) {
+        image = pad(image);
+    }
+
+    if let Some(path) = &options.debug_path {
+        image
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|e| format!("Failed to write debug image '{}': {}", path.display(), e))?;
+    }
+
+    Ok(image)
+}
+
+// --- Individual Steps ---
+
+fn upscale(image: DynamicImage) -> DynamicImage {
+    let height = image.height().max(1);
+    if height >= MIN_OCR_HEIGHT {
+        return image;
+    }
+
+    let factor = MIN_OCR_HEIGHT.div_ceil(height).min(MAX_UPSCALE);
+    image.resize(
+        image.width() * factor,
+        height * factor,
+        FilterType::CatmullRom,
+    )
+}
+
+/// Estimates the background brightness from the outermost pixels, which are
+/// almost always background in a capture selected around some text.
+fn has_dark_background(gray: &GrayImage) -> bool {
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return false;
+    }
+
+    let mut total: u64 = 0;
+    let mut count: u64 = 0;
+    for (x, y, pixel) in gray.enumerate_pixels() {
+        if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+            total += pixel[0] as u64;
+            count += 1;
+        }
+    }
+    total / count.max(1) < 128
+}
+
+fn otsu_threshold(gray: &GrayImage) -> GrayImage {
+    let mut histogram = [0u64; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+
+    let total = gray.pixels().len() as f64;
+    let sum_all: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, &count)| value as f64 * count as f64)
+        .sum();
+
+    let mut sum_background = 0.0;
+    let mut weight_background = 0.0;
+    let mut best_variance = 0.0;
+    let mut threshold = 127u8;
+    for (value, &count) in histogram.iter().enumerate() {
+        weight_background += count as f64;
+        if weight_background == 0.0 {
+            continue;
+        }
+        let weight_foreground = total - weight_background;
+        if weight_foreground == 0.0 {
+            break;
+        }
+
+        sum_background += value as f64 * count as f64;
+        let mean_background = sum_background / weight_background;
+        let mean_foreground = (sum_all - sum_background) / weight_foreground;
+        let variance =
+            weight_background * weight_foreground * (mean_background - mean_foreground).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            threshold = value as u8;
+        }
+    }
+
+    let mut out = gray.clone();
+    for pixel in out.pixels_mut() {
+        pixel[0] = if pixel[0] > threshold { 255 } else { 0 };
+    }
+    out
+}
+
+fn adaptive_threshold(gray: &GrayImage) -> GrayImage {
+    let (width, height) = gray.dimensions();
+    let stride = width as usize + 1;
+
+    // Summed-area table, so each window mean costs four lookups.
+    let mut integral = vec![0u64; stride * (height as usize + 1)];
+    for y in 0..height as usize {
+        let mut row_sum = 0u64;
+        for x in 0..width as usize {
+            row_sum += gray.get_pixel(x as u32, y as u32)[0] as u64;
+            integral[(y + 1) * stride + x + 1] = integral[y * stride + x + 1] + row_sum;
+        }
+    }
+
+    let radius = ADAPTIVE_WINDOW / 2;
+    let mut out = GrayImage::new(width, height);
+    for (x, y, pixel) in gray.enumerate_pixels() {
+        let x0 = x.saturating_sub(radius) as usize;
+        let y0 = y.saturating_sub(radius) as usize;
+        let x1 = (x + radius + 1).min(width) as usize;
+        let y1 = (y + radius + 1).min(height) as usize;
+
+        let sum = integral[y1 * stride + x1] + integral[y0 * stride + x0]
+            - integral[y0 * stride + x1]
+            - integral[y1 * stride + x0];
+        let area = ((x1 - x0) * (y1 - y0)) as u64;
+        let mean = (sum / area.max(1)) as i32;
+
+        let value = if (pixel[0] as i32) < mean - ADAPTIVE_OFFSET {
+            0
+        } else {
+            255
+        };
+        out.put_pixel(x, y, Luma([value]));
+    }
+    out
+}
+
+/// Adds a border in the background colour, estimated from the image edges.
+fn pad(image: DynamicImage) -> DynamicImage {
+    let width = image.width() + PADDING * 2;
+    let height = image.height() + PADDING * 2;
+
+    match image {
+        DynamicImage::ImageLuma8(gray) => {
+            let background = if has_dark_background(&gray) { 0 } else { 255 };
+            let mut out = GrayImage::from_pixel(width, height, Luma([background]));
+            imageops::overlay(&mut out, &gray, PADDING as i64, PADDING as i64);
+            DynamicImage::ImageLuma8(out)
+        }
+        other => {
+            let rgb = other.to_rgb8();
+            let background = edge_color(&rgb);
+            let mut out = RgbImage::from_pixel(width, height, background);
+            imageops::overlay(&mut out, &rgb, PADDING as i64, PADDING as i64);
+            DynamicImage::ImageRgb8(out)
+        }
+    }
+}
+
+fn edge_color(rgb: &RgbImage) -> Rgb<u8> {
+    let (width, height) = rgb.dimensions();
+    let mut totals = [0u64; 3];
+    let mut count = 0u64;
+    for (x, y, pixel) in rgb.enumerate_pixels() {
+        if x == 0 || y == 0 || x + 1 == width || y + 1 == height {
+            for (total, channel) in totals.iter_mut().zip(pixel.0) {
+                *total += channel as u64;
+            }
+            count += 1;
+        }
+    }
+    let count = count.max(1);
+    Rgb(totals.map(|total| (total / count) as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `width`×`height` image of `background` with a `text` block in the middle.
+    fn text_image(width: u32, height: u32, background: u8, text: u8) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let inside =
+                x >= width / 4 && x < width * 3 / 4 && y >= height / 4 && y < height * 3 / 4;
+            Luma([if inside { text } else { background }])
+        })
+    }
+
+    fn options(steps: &[PreprocessStep]) -> PreprocessOptions {
+        PreprocessOptions {
+            steps: steps.to_vec(),
+            debug_path: None,
+        }
+    }
+
+    #[test]
+    fn otsu_splits_text_from_background() {
+        let mut gray = text_image(40, 40, 200, 60);
+        // Some noise on both sides of the threshold.
+        gray.put_pixel(0, 0, Luma([180]));
+        gray.put_pixel(20, 20, Luma([90]));
+
+        let binary = otsu_threshold(&gray);
+        assert!(binary.pixels().all(|p| p[0] == 0 || p[0] == 255));
+        assert_eq!(binary.get_pixel(0, 0)[0], 255);
+        assert_eq!(binary.get_pixel(2, 2)[0], 255);
+        assert_eq!(binary.get_pixel(20, 20)[0], 0);
+        assert_eq!(binary.get_pixel(15, 15)[0], 0);
+    }
+
+    #[test]
+    fn dark_background_is_detected_from_the_edges() {
+        assert!(has_dark_background(&text_image(40, 40, 20, 230)));
+        assert!(!has_dark_background(&text_image(40, 40, 230, 20)));
+        assert!(!has_dark_background(&GrayImage::new(0, 0)));
+    }
+
+    #[test]
+    fn invert_only_flips_dark_backgrounds() {
+        let steps = [PreprocessStep::Grayscale, PreprocessStep::Invert];
+        let dark = DynamicImage::ImageLuma8(text_image(40, 400, 20, 230));
+        let out = preprocess(dark, &options(&steps)).unwrap().to_luma8();
+        assert_eq!(out.get_pixel(0, 0)[0], 235);
+        assert_eq!(out.get_pixel(20, 200)[0], 25);
+
+        let light = DynamicImage::ImageLuma8(text_image(40, 400, 230, 20));
+        let out = preprocess(light, &options(&steps)).unwrap().to_luma8();
+        assert_eq!(out.get_pixel(0, 0)[0], 230);
+    }
+
+    #[test]
+    fn upscale_and_pad_change_the_size() {
+        let small = DynamicImage::ImageLuma8(text_image(50, 20, 255, 0));
+        let out = preprocess(
+            small,
+            &options(&[PreprocessStep::Upscale, PreprocessStep::Pad]),
+        )
+        .unwrap();
+        // 20px high needs 15x to reach 300px, capped at 4x.
+        assert_eq!(out.height(), 20 * MAX_UPSCALE + 2 * PADDING);
+        assert_eq!(out.width(), 50 * MAX_UPSCALE + 2 * PADDING);
+
+        let none = DynamicImage::ImageLuma8(text_image(50, 20, 255, 0));
+        let out = preprocess(none, &options(&[PreprocessStep::None, PreprocessStep::Pad])).unwrap();
+        assert_eq!((out.width(), out.height()), (50, 20));
+    }
+}
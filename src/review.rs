@@ -0,0 +1,164 @@
+use crate::app::{
+    render_bullet_point, render_example_item, render_section_header, render_translation_item,
+};
+use crate::vocab::{Grade, Vocabulary};
+use eframe::egui;
+use std::collections::VecDeque;
+
+/// Quizzes the saved words that are due, one card at a time.
+pub struct ReviewApp {
+    vocabulary: Vocabulary,
+    /// Indices into `vocabulary.cards` still to be shown in this session.
+    queue: VecDeque<usize>,
+    show_answer: bool,
+    reviewed: usize,
+}
+
+impl ReviewApp {
+    pub fn new(vocabulary: Vocabulary) -> Self {
+        let queue = vocabulary.due_cards().into();
+        Self {
+            vocabulary,
+            queue,
+            show_answer: false,
+            reviewed: 0,
+        }
+    }
+
+    fn grade(&mut self, grade: Grade) {
+        let Some(index) = self.queue.pop_front() else {
+            return;
+        };
+        self.vocabulary.review(index, grade);
+        if let Err(e) = self.vocabulary.save() {
+            eprintln!("Failed to save the review schedule: {}", e);
+        }
+
+        // Forgotten words come back at the end of this session as well.
+        if grade == Grade::Again {
+            self.queue.push_back(index);
+        } else {
+            self.reviewed += 1;
+        }
+        self.show_answer = false;
+    }
+}
+
+impl eframe::App for ReviewApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Keyboard: Space shows the answer, 1-4 grade it.
+        let mut grade = None;
+        ctx.input(|i| {
+            if i.key_pressed(egui::Key::Space) {
+                self.show_answer = true;
+            } else if self.show_answer {
+                grade = [
+                    (egui::Key::Num1, Grade::Again),
+                    (egui::Key::Num2, Grade::Hard),
+                    (egui::Key::Num3, Grade::Good),
+                    (egui::Key::Num4, Grade::Easy),
+                ]
+                .into_iter()
+                .find(|(key, _)| i.key_pressed(*key))
+                .map(|(_, grade)| grade);
+            }
+        });
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame {
+                fill: ctx.style().visuals.panel_fill,
+                inner_margin: egui::Margin::same(16.0),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                let Some(&index) = self.queue.front() else {
+                    // Finished View
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(40.0);
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "All done! {} words reviewed.",
+                                self.reviewed
+                            ))
+                            .size(18.0)
+                            .color(ui.visuals().strong_text_color()),
+                        );
+                        ui.add_space(10.0);
+                        if ui.button("Close").clicked() {
+                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                        }
+                    });
+                    return;
+                };
+                let card = &self.vocabulary.cards[index];
+
+                ui.label(
+                    egui::RichText::new(format!("{} left", self.queue.len()))
+                        .size(12.0)
+                        .color(ui.visuals().weak_text_color()),
+                );
+                ui.label(
+                    egui::RichText::new(&card.word)
+                        .size(28.0)
+                        .strong()
+                        .color(ui.visuals().strong_text_color()),
+                );
+                ui.add(egui::Separator::default().spacing(6.0));
+
+                if !self.show_answer {
+                    if ui.button("Show answer (Space)").clicked() {
+                        self.show_answer = true;
+                    }
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    for (label, value) in [
+                        ("1 Again", Grade::Again),
+                        ("2 Hard", Grade::Hard),
+                        ("3 Good", Grade::Good),
+                        ("4 Easy", Grade::Easy),
+                    ] {
+                        if ui.button(label).clicked() {
+                            grade = Some(value);
+                        }
+                    }
+                });
+                ui.add_space(6.0);
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.set_max_width(ui.available_width());
+
+                    if !card.translation.is_empty() {
+                        render_section_header(
+                            ui,
+                            &format!("Translation ({}):", card.target_lang.to_uppercase()),
+                        );
+                        render_bullet_point(ui, &card.translation);
+                        ui.add_space(10.0);
+                    }
+                    if !card.definitions.is_empty() {
+                        render_section_header(ui, "Definitions:");
+                        for item in &card.definitions {
+                            render_translation_item(ui, item);
+                        }
+                        ui.add_space(10.0);
+                    }
+                    if !card.examples.is_empty() {
+                        render_section_header(ui, "Example Sentences:");
+                        for ex in card.examples.iter().take(2) {
+                            render_example_item(ui, ex, &card.source_lang, &card.target_lang);
+                        }
+                    }
+                });
+            });
+
+        if let Some(grade) = grade {
+            self.grade(grade);
+        }
+    }
+
+    fn clear_color(&self, visuals: &egui::Visuals) -> [f32; 4] {
+        visuals.panel_fill.to_normalized_gamma_f32()
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// The writing systems the bundled OCR languages cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Script {
+    Latin,
+    Cyrillic,
+    /// Chinese characters without any kana.
+    Han,
+    /// Kana, possibly mixed with kanji.
+    Japanese,
+    Hangul,
+    Thai,
+    Unknown,
+}
+
+impl Script {
+    /// Returns the script a single character belongs to, if it is one we know.
+    pub fn of_char(c: char) -> Option<Script> {
+        match c as u32 {
+            0x0041..=0x005A | 0x0061..=0x007A | 0x00C0..=0x024F | 0x1E00..=0x1EFF => {
+                Some(Script::Latin)
+            }
+            0x0400..=0x052F => Some(Script::Cyrillic),
+            0x0E00..=0x0E7F => Some(Script::Thai),
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Some(Script::Japanese),
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Some(Script::Hangul),
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => Some(Script::Han),
+            _ => None,
+        }
+    }
+
+    /// Returns the dominant script of a piece of text.
+    pub fn detect(text: &str) -> Script {
+        Script::ranked(text)
+            .first()
+            .map_or(Script::Unknown, |(script, _)| *script)
+    }
+
+    /// Counts the characters of each script in the text, most frequent first.
+    /// Han characters count towards Japanese as soon as any kana is present.
+    pub fn ranked(text: &str) -> Vec<(Script, usize)> {
+        let mut counts: Vec<(Script, usize)> = Vec::new();
+        for script in text.chars().filter_map(Script::of_char) {
+            match counts.iter_mut().find(|(s, _)| *s == script) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((script, 1)),
+            }
+        }
+
+        let has_kana = counts.iter().any(|(s, _)| *s == Script::Japanese);
+        if has_kana {
+            let han = counts
+                .iter()
+                .find(|(s, _)| *s == Script::Han)
+                .map_or(0, |(_, n)| *n);
+            counts.retain(|(s, _)| *s != Script::Han);
+            if let Some((_, count)) = counts.iter_mut().find(|(s, _)| *s == Script::Japanese) {
+                *count += han;
+            }
+        }
+
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        counts
+    }
+
+    /// Whether the script is written without spaces between words.
+    pub fn is_spaceless(self) -> bool {
+        matches!(self, Script::Han | Script::Japanese | Script::Thai)
+    }
+}
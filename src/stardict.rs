@@ -0,0 +1,678 @@
+use crate::translation::{
+    is_single_word, parse_definition, CombinedTranslationData, ProviderSection, TranslateResult,
+    TranslationItem, TranslationProvider, TranslationQuery,
+};
+use flate2::read::GzDecoder;
+use flate2::{Decompress, FlushDecompress};
+use futures_util::future::BoxFuture;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// At most this many definition lines are shown per dictionary.
+const MAX_ENTRIES_PER_DICT: usize = 10;
+
+// --- Dictionary Files ---
+
+/// One StarDict dictionary: an `.ifo` description, an `.idx` word index and
+/// a `.dict` (or dictzip-compressed `.dict.dz`) data file.
+pub struct StarDict {
+    name: String,
+    same_type_sequence: Option<String>,
+    index: Vec<IndexEntry>,
+    /// Positions in `index` by lowercased word, built once when the dictionary is opened.
+    by_word: HashMap<String, Vec<usize>>,
+    dict_path: PathBuf,
+    /// Chunk table of a `.dict.dz` file, so entries are read without
+    /// decompressing the whole file.
+    dictzip: Option<DictZip>,
+    /// Decompressed contents of a `.dict.dz` file without a chunk table (plain
+    /// gzip), loaded on the first lookup.
+    dz_data: OnceLock<Option<Vec<u8>>>,
+}
+
+struct IndexEntry {
+    word: String,
+    offset: u64,
+    size: u32,
+}
+
+impl StarDict {
+    /// Loads a dictionary from the path of its `.ifo` file.
+    pub fn open(ifo_path: &Path) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let ifo = fs::read_to_string(ifo_path)?;
+        if !ifo.starts_with("StarDict's dict ifo file") {
+            return Err(format!("{} is not a StarDict .ifo file", ifo_path.display()).into());
+        }
+
+        let fields: HashMap<&str, &str> = ifo
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+        let offset_bits: u32 = fields
+            .get("idxoffsetbits")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(32);
+
+        let idx_path = ifo_path.with_extension("idx");
+        let idx_gz_path = ifo_path.with_extension("idx.gz");
+        let idx_data = if idx_path.exists() {
+            fs::read(&idx_path)?
+        } else {
+            let mut data = Vec::new();
+            GzDecoder::new(File::open(&idx_gz_path)?).read_to_end(&mut data)?;
+            data
+        };
+
+        let dict_path = ifo_path.with_extension("dict");
+        let dict_path = if dict_path.exists() {
+            dict_path
+        } else {
+            ifo_path.with_extension("dict.dz")
+        };
+        if !dict_path.exists() {
+            return Err(format!("No .dict or .dict.dz file next to {}", ifo_path.display()).into());
+        }
+
+        let dictzip = if dict_path.extension().is_some_and(|ext| ext == "dz") {
+            DictZip::open(&dict_path)?
+        } else {
+            None
+        };
+
+        let index = parse_index(&idx_data, offset_bits == 64);
+        let mut by_word: HashMap<String, Vec<usize>> = HashMap::new();
+        for (position, entry) in index.iter().enumerate() {
+            by_word
+                .entry(entry.word.to_lowercase())
+                .or_default()
+                .push(position);
+        }
+
+        Ok(Self {
+            name: fields
+                .get("bookname")
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| file_stem(ifo_path)),
+            same_type_sequence: fields.get("sametypesequence").map(|s| s.to_string()),
+            index,
+            by_word,
+            dict_path,
+            dictzip,
+            dz_data: OnceLock::new(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the definitions of `word`. Exact matches win over case-insensitive ones.
+    pub fn lookup(&self, word: &str) -> Vec<String> {
+        let candidates: Vec<&IndexEntry> = self
+            .by_word
+            .get(&word.to_lowercase())
+            .into_iter()
+            .flatten()
+            .map(|&position| &self.index[position])
+            .collect();
+        let exact: Vec<&IndexEntry> = candidates
+            .iter()
+            .copied()
+            .filter(|e| e.word == word)
+            .collect();
+        let matches = if exact.is_empty() { candidates } else { exact };
+
+        matches
+            .into_iter()
+            .filter_map(|entry| self.read_entry(entry).ok())
+            .map(|data| self.decode_entry(&data))
+            .filter(|text| !text.is_empty())
+            .collect()
+    }
+
+    fn read_entry(&self, entry: &IndexEntry) -> io::Result<Vec<u8>> {
+        let start = entry.offset as usize;
+        let end = start + entry.size as usize;
+
+        if let Some(dictzip) = &self.dictzip {
+            return dictzip.read(&self.dict_path, entry.offset, entry.size as usize);
+        }
+        if self.dict_path.extension().is_some_and(|ext| ext == "dz") {
+            let data = self
+                .dz_data
+                .get_or_init(|| {
+                    let mut data = Vec::new();
+                    GzDecoder::new(File::open(&self.dict_path).ok()?)
+                        .read_to_end(&mut data)
+                        .ok()?;
+                    Some(data)
+                })
+                .as_ref()
+                .ok_or_else(|| io::Error::other("Failed to decompress .dict.dz file"))?;
+            return data
+                .get(start..end)
+                .map(|slice| slice.to_vec())
+                .ok_or_else(|| io::Error::other("Index entry points past the end of the data"));
+        }
+
+        let mut file = File::open(&self.dict_path)?;
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let mut data = vec![0; entry.size as usize];
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    /// Extracts the readable text fields of an entry, stripping any markup.
+    fn decode_entry(&self, data: &[u8]) -> String {
+        let fields = match &self.same_type_sequence {
+            Some(types) => split_typed_fields(data, types.as_bytes()),
+            None => split_tagged_fields(data),
+        };
+
+        fields
+            .into_iter()
+            .filter_map(|(kind, bytes)| {
+                let text = String::from_utf8_lossy(bytes);
+                match kind {
+                    b'm' | b'l' | b'y' | b'k' | b'w' => Some(text.into_owned()),
+                    b'g' | b'h' | b'x' => Some(strip_markup(&text)),
+                    _ => None, // Phonetics, images, sounds, ...
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string()
+    }
+}
+
+// --- Dictzip ---
+
+/// The chunk table of a dictzip file: a gzip file whose deflate stream is
+/// fully flushed every `chunk_len` bytes of output, so each chunk can be
+/// inflated on its own. The table is stored in the gzip header's `RA` field.
+struct DictZip {
+    chunk_len: usize,
+    /// File offset of each chunk, followed by the end of the last one.
+    chunk_offsets: Vec<u64>,
+}
+
+impl DictZip {
+    /// Reads the chunk table, or `None` for a plain gzip file without one.
+    fn open(path: &Path) -> io::Result<Option<Self>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0; 10];
+        reader.read_exact(&mut header)?;
+        if header[..3] != [0x1f, 0x8b, 8] {
+            return Err(io::Error::other(format!(
+                "{} is not a gzip file",
+                path.display()
+            )));
+        }
+        let flags = header[3];
+        if flags & FEXTRA == 0 {
+            return Ok(None);
+        }
+
+        let mut len = [0; 2];
+        reader.read_exact(&mut len)?;
+        let mut extra = vec![0; u16::from_le_bytes(len) as usize];
+        reader.read_exact(&mut extra)?;
+        let mut header_len = 12 + extra.len() as u64;
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                let mut skipped = Vec::new();
+                header_len += reader.read_until(0, &mut skipped)? as u64;
+            }
+        }
+        if flags & FHCRC != 0 {
+            header_len += 2;
+        }
+
+        Ok(parse_ra_field(&extra).map(|(chunk_len, sizes)| {
+            let mut chunk_offsets = vec![header_len];
+            for size in sizes {
+                let last = chunk_offsets[chunk_offsets.len() - 1];
+                chunk_offsets.push(last + size as u64);
+            }
+            Self {
+                chunk_len,
+                chunk_offsets,
+            }
+        }))
+    }
+
+    /// Reads `size` bytes at `offset` of the uncompressed data, inflating only
+    /// the chunks they lie in.
+    fn read(&self, path: &Path, offset: u64, size: usize) -> io::Result<Vec<u8>> {
+        let chunks = self.chunk_offsets.len() - 1;
+        let first = offset as usize / self.chunk_len;
+        let last = (offset as usize + size.max(1) - 1) / self.chunk_len;
+        if last >= chunks {
+            return Err(io::Error::other(
+                "Index entry points past the end of the data",
+            ));
+        }
+
+        let mut file = File::open(path)?;
+        let mut data = Vec::with_capacity((last - first + 1) * self.chunk_len);
+        for chunk in first..=last {
+            let start = self.chunk_offsets[chunk];
+            let mut compressed = vec![0; (self.chunk_offsets[chunk + 1] - start) as usize];
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut compressed)?;
+
+            let mut inflated = Vec::with_capacity(self.chunk_len);
+            Decompress::new(false)
+                .decompress_vec(&compressed, &mut inflated, FlushDecompress::Sync)
+                .map_err(io::Error::other)?;
+            data.extend_from_slice(&inflated);
+        }
+
+        let start = offset as usize - first * self.chunk_len;
+        data.get(start..start + size)
+            .map(|slice| slice.to_vec())
+            .ok_or_else(|| io::Error::other("Index entry points past the end of the data"))
+    }
+}
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+/// Finds the `RA` subfield in a gzip extra field and returns the chunk length
+/// and the compressed size of each chunk.
+fn parse_ra_field(mut extra: &[u8]) -> Option<(usize, Vec<u16>)> {
+    while extra.len() >= 4 {
+        let len = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+        let data = extra.get(4..4 + len)?;
+        if extra[..2] == *b"RA" && data.len() >= 6 {
+            let word = |i: usize| u16::from_le_bytes([data[2 * i], data[2 * i + 1]]);
+            let (chunk_len, count) = (word(1) as usize, word(2) as usize);
+            if chunk_len == 0 || data.len() < 6 + 2 * count {
+                return None;
+            }
+            return Some((chunk_len, (0..count).map(|i| word(3 + i)).collect()));
+        }
+        extra = &extra[4 + len..];
+    }
+    None
+}
+
+/// Finds every `.ifo` file below `dir` and loads the dictionaries that parse.
+pub fn load_dictionaries(dir: &Path) -> Vec<StarDict> {
+    let mut ifo_paths = Vec::new();
+    collect_ifo_files(dir, &mut ifo_paths);
+    ifo_paths.sort();
+    ifo_paths
+        .iter()
+        .filter_map(|path| StarDict::open(path).ok())
+        .collect()
+}
+
+fn collect_ifo_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_ifo_files(&path, out);
+        } else if path.extension().is_some_and(|ext| ext == "ifo") {
+            out.push(path);
+        }
+    }
+}
+
+// --- Provider ---
+
+/// Looks single words up in local StarDict dictionaries. Works without a network.
+/// The dictionaries are opened on the first lookup, so runs that never reach
+/// this provider (e.g. multi-word text) don't pay for reading the indexes.
+pub struct StarDictProvider {
+    dir: PathBuf,
+    dictionaries: OnceLock<Vec<StarDict>>,
+}
+
+impl StarDictProvider {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            dictionaries: OnceLock::new(),
+        }
+    }
+
+    fn dictionaries(&self) -> &[StarDict] {
+        self.dictionaries
+            .get_or_init(|| load_dictionaries(&self.dir))
+    }
+}
+
+impl TranslationProvider for StarDictProvider {
+    fn name(&self) -> &'static str {
+        "stardict"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        query: TranslationQuery<'a>,
+        data: &'a mut CombinedTranslationData,
+    ) -> BoxFuture<'a, TranslateResult<()>> {
+        Box::pin(async move {
+            if !is_single_word(query.text) {
+                return Ok(());
+            }
+
+            for dict in self.dictionaries() {
+                let entries: Vec<TranslationItem> = dict
+                    .lookup(query.text)
+                    .iter()
+                    .flat_map(|definition| definition.lines())
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .take(MAX_ENTRIES_PER_DICT)
+                    .map(|line| {
+                        let (pos, translation) = parse_definition(line);
+                        TranslationItem {
+                            word: query.text.to_string(),
+                            pos,
+                            translation,
+                            dictionary: dict.name().to_string(),
+                        }
+                    })
+                    .collect();
+
+                if !entries.is_empty() {
+                    data.sections.push(ProviderSection {
+                        provider: dict.name().to_string(),
+                        translation: None,
+                        entries,
+                    });
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+// --- Parsing Helpers ---
+
+/// Parses the `.idx` file: a NUL-terminated word followed by a big-endian
+/// offset (32 or 64 bits) and a big-endian 32-bit size, repeated.
+fn parse_index(data: &[u8], offset_64: bool) -> Vec<IndexEntry> {
+    let offset_len = if offset_64 { 8 } else { 4 };
+    let mut entries = Vec::new();
+    let mut pos = 0;
+
+    while let Some(nul) = data[pos..].iter().position(|&b| b == 0) {
+        let word = String::from_utf8_lossy(&data[pos..pos + nul]).into_owned();
+        pos += nul + 1;
+        if pos + offset_len + 4 > data.len() {
+            break;
+        }
+
+        let offset = if offset_64 {
+            u64::from_be_bytes(data[pos..pos + 8].try_into().unwrap_or_default())
+        } else {
+            u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap_or_default()) as u64
+        };
+        pos += offset_len;
+        let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap_or_default());
+        pos += 4;
+
+        entries.push(IndexEntry { word, offset, size });
+    }
+    entries
+}
+
+/// Splits entry data when the `.ifo` declares `sametypesequence`: the type
+/// characters are omitted, and the last field runs to the end of the data.
+fn split_typed_fields<'a>(data: &'a [u8], types: &[u8]) -> Vec<(u8, &'a [u8])> {
+    let mut fields = Vec::new();
+    let mut rest = data;
+    for (i, &kind) in types.iter().enumerate() {
+        let is_last = i == types.len() - 1;
+        let (field, remaining) = if is_last {
+            (rest, &rest[rest.len()..])
+        } else {
+            split_field(rest, kind)
+        };
+        fields.push((kind, field));
+        rest = remaining;
+    }
+    fields
+}
+
+/// Splits entry data where each field is prefixed by its type character.
+fn split_tagged_fields(data: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut fields = Vec::new();
+    let mut rest = data;
+    while let Some((&kind, remaining)) = rest.split_first() {
+        let (field, remaining) = split_field(remaining, kind);
+        fields.push((kind, field));
+        rest = remaining;
+    }
+    fields
+}
+
+/// Lowercase types are NUL-terminated strings; uppercase types are prefixed
+/// by a big-endian 32-bit length.
+fn split_field(data: &[u8], kind: u8) -> (&[u8], &[u8]) {
+    if kind.is_ascii_uppercase() {
+        if data.len() < 4 {
+            return (&data[data.len()..], &data[data.len()..]);
+        }
+        let len = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let end = (4 + len).min(data.len());
+        (&data[4..end], &data[end..])
+    } else {
+        match data.iter().position(|&b| b == 0) {
+            Some(nul) => (&data[..nul], &data[nul + 1..]),
+            None => (data, &data[data.len()..]),
+        }
+    }
+}
+
+fn strip_markup(text: &str) -> String {
+    static TAG_RE: OnceLock<Regex> = OnceLock::new();
+    let tag_re =
+        TAG_RE.get_or_init(|| Regex::new(r"<br\s*/?>|</?(p|div|li)[^>]*>|<[^>]+>").unwrap());
+
+    let text = tag_re.replace_all(text, |caps: &regex::Captures| {
+        if caps[0].starts_with("<br") || caps.get(1).is_some() {
+            "\n"
+        } else {
+            ""
+        }
+    });
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+    use flate2::{Compress, Compression, FlushCompress};
+
+    /// Writes `name.ifo`, `name.idx` and the dictionary data (`.dict`, or
+    /// `.dict.dz` with `chunk_len`) for `words` and returns the `.ifo` path.
+    fn write_dictionary(
+        dir: &Path,
+        name: &str,
+        words: &[(&str, &str)],
+        chunk_len: Option<usize>,
+    ) -> PathBuf {
+        let mut idx = Vec::new();
+        let mut dict = Vec::new();
+        for (word, definition) in words {
+            idx.extend_from_slice(word.as_bytes());
+            idx.push(0);
+            idx.extend_from_slice(&(dict.len() as u32).to_be_bytes());
+            idx.extend_from_slice(&(definition.len() as u32).to_be_bytes());
+            dict.extend_from_slice(definition.as_bytes());
+        }
+
+        let ifo_path = dir.join(format!("{}.ifo", name));
+        fs::write(
+            &ifo_path,
+            format!(
+                "StarDict's dict ifo file\nversion=2.4.2\nwordcount={}\nbookname=Test {}\nsametypesequence=m\n",
+                words.len(),
+                name
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join(format!("{}.idx", name)), idx).unwrap();
+        match chunk_len {
+            Some(chunk_len) => fs::write(
+                dir.join(format!("{}.dict.dz", name)),
+                dictzip(&dict, chunk_len),
+            )
+            .unwrap(),
+            None => fs::write(dir.join(format!("{}.dict", name)), dict).unwrap(),
+        }
+        ifo_path
+    }
+
+    /// Compresses `data` the way `dictzip` does: one fully flushed deflate
+    /// block per chunk, with the chunk sizes in the `RA` extra field.
+    fn dictzip(data: &[u8], chunk_len: usize) -> Vec<u8> {
+        let mut compress = Compress::new(Compression::default(), false);
+        let pieces: Vec<&[u8]> = data.chunks(chunk_len).collect();
+        let chunks: Vec<Vec<u8>> = pieces
+            .iter()
+            .enumerate()
+            .map(|(i, piece)| {
+                let flush = if i == pieces.len() - 1 {
+                    FlushCompress::Finish
+                } else {
+                    FlushCompress::Full
+                };
+                let mut out = Vec::with_capacity(piece.len() * 2 + 64);
+                compress.compress_vec(piece, &mut out, flush).unwrap();
+                out
+            })
+            .collect();
+
+        let mut ra = vec![1, 0];
+        ra.extend_from_slice(&(chunk_len as u16).to_le_bytes());
+        ra.extend_from_slice(&(chunks.len() as u16).to_le_bytes());
+        for chunk in &chunks {
+            ra.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+        }
+        let mut extra = b"RA".to_vec();
+        extra.extend_from_slice(&(ra.len() as u16).to_le_bytes());
+        extra.extend_from_slice(&ra);
+
+        let mut file = vec![0x1f, 0x8b, 8, FEXTRA | FNAME, 0, 0, 0, 0, 0, 3];
+        file.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        file.extend_from_slice(&extra);
+        file.extend_from_slice(b"test.dict\0");
+        for chunk in &chunks {
+            file.extend_from_slice(chunk);
+        }
+        file.extend_from_slice(&[0; 8]); // CRC32 and size, not checked
+        file
+    }
+
+    #[test]
+    fn parses_32_and_64_bit_index() {
+        let mut idx = b"cat\0".to_vec();
+        idx.extend_from_slice(&7u32.to_be_bytes());
+        idx.extend_from_slice(&3u32.to_be_bytes());
+        let entries = parse_index(&idx, false);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            (entries[0].word.as_str(), entries[0].offset, entries[0].size),
+            ("cat", 7, 3)
+        );
+
+        let mut idx = b"dog\0".to_vec();
+        idx.extend_from_slice(&(1u64 << 33).to_be_bytes());
+        idx.extend_from_slice(&5u32.to_be_bytes());
+        idx.extend_from_slice(b"truncated\0\0\0");
+        let entries = parse_index(&idx, true);
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].offset, entries[0].size), (1 << 33, 5));
+    }
+
+    #[test]
+    fn reads_ifo_and_prefers_exact_matches() {
+        let dir = paths::test_dir("stardict-plain");
+        let ifo = write_dictionary(
+            &dir,
+            "plain",
+            &[
+                ("Apple", "a company"),
+                ("apple", "a fruit"),
+                ("pear", "another fruit"),
+            ],
+            None,
+        );
+        let dict = StarDict::open(&ifo).unwrap();
+
+        assert_eq!(dict.name(), "Test plain");
+        assert_eq!(dict.lookup("apple"), vec!["a fruit"]);
+        assert_eq!(dict.lookup("APPLE"), vec!["a company", "a fruit"]);
+        assert!(dict.lookup("plum").is_empty());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let dir = paths::test_dir("stardict-invalid");
+        let path = dir.join("notes.ifo");
+        fs::write(&path, "just some notes").unwrap();
+        assert!(StarDict::open(&path).is_err());
+    }
+
+    #[test]
+    fn reads_entries_across_dictzip_chunks() {
+        let dir = paths::test_dir("stardict-dz");
+        let words: Vec<(String, String)> = (0..20)
+            .map(|i| (format!("word{:02}", i), format!("definition number {}", i)))
+            .collect();
+        let words: Vec<(&str, &str)> = words
+            .iter()
+            .map(|(word, definition)| (word.as_str(), definition.as_str()))
+            .collect();
+        // Chunks of 16 bytes, so most definitions span two of them.
+        let ifo = write_dictionary(&dir, "zipped", &words, Some(16));
+        let dict = StarDict::open(&ifo).unwrap();
+
+        assert!(dict.dictzip.is_some());
+        assert_eq!(dict.lookup("word00"), vec!["definition number 0"]);
+        assert_eq!(dict.lookup("word13"), vec!["definition number 13"]);
+        assert_eq!(dict.lookup("word19"), vec!["definition number 19"]);
+    }
+
+    #[test]
+    fn strips_markup_from_html_fields() {
+        let mut data = b"h<b>bold</b><br>next &amp; last\0".to_vec();
+        data.extend_from_slice(b"mplain\0");
+        let fields = split_tagged_fields(&data);
+        assert_eq!(fields.len(), 2);
+        assert_eq!(
+            strip_markup(&String::from_utf8_lossy(fields[0].1)),
+            "bold\nnext & last"
+        );
+        assert_eq!(fields[1], (b'm', &b"plain"[..]));
+    }
+}
@@ -0,0 +1,486 @@
+use crate::paths;
+use include_dir::{include_dir, Dir};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// Embed the 'tessdata' directory directly into the binary.
+// This requires a `tessdata` folder in the project's root directory.
+static BUNDLED: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/tessdata");
+
+const EXTENSION: &str = "traineddata";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Tessdata files that are not recognition languages: orientation/script
+/// detection and the old equation model.
+const NON_LANGUAGES: &[&str] = &["osd", "equ"];
+
+type TessdataResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+// --- Data Structures ---
+
+/// Written next to the extracted files. Records which files came from the
+/// binary, so they can be refreshed after an upgrade or when they are damaged,
+/// and told apart from language packs the user added.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Manifest {
+    /// Version of the program that extracted the files.
+    version: String,
+    files: BTreeMap<String, ManifestEntry>,
+    /// Files that are symlinks into the custom tessdata directory.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    linked: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ManifestEntry {
+    /// SHA-256 of the bundled file, in hex.
+    sha256: String,
+    /// Size and modification time of the file on disk right after it was checked.
+    /// As long as these are unchanged, the file is not hashed again.
+    stamp: Stamp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Stamp {
+    size: u64,
+    modified_ns: u128,
+}
+
+/// A `.traineddata` file in the tessdata directory.
+pub struct LanguagePack {
+    /// Tesseract's name for the language, e.g. `eng` or `chi_sim`.
+    pub code: String,
+    pub size: u64,
+    /// Extracted from the binary, as opposed to installed by the user.
+    pub bundled: bool,
+    /// A link to a file in the custom tessdata directory (see `setup`).
+    pub linked: bool,
+}
+
+// --- Setup ---
+
+/// The directory the bundled languages are extracted to, e.g.
+/// `~/.local/share/floating-dictionary-linux/tessdata`. Language packs the user
+/// installs go here as well.
+pub fn default_dir() -> io::Result<PathBuf> {
+    Ok(paths::app_data_dir()?.join("tessdata"))
+}
+
+/// Prepares the Tesseract data directory and points `TESSDATA_PREFIX` at it.
+///
+/// The bundled `.traineddata` files are extracted to `default_dir()`, and any
+/// that are missing, outdated or damaged are written again. The languages in
+/// `custom_dir` (e.g. `/usr/share/tesseract/tessdata`) are added to them as
+/// symlinks (see `link_custom`). Returns the directory in use.
+pub fn setup(custom_dir: Option<&Path>) -> TessdataResult<PathBuf> {
+    let dir = default_dir()?;
+    sync_bundled(&dir)?;
+    match custom_dir {
+        Some(custom) if !custom.is_dir() => {
+            return Err(
+                format!("Tessdata directory '{}' does not exist.", custom.display()).into(),
+            );
+        }
+        _ => link_custom(&dir, custom_dir)?,
+    }
+
+    // Tell Tesseract where to find the data files.
+    // The TESSDATA_PREFIX variable should point to the directory
+    // containing the `.traineddata` files directly.
+    env::set_var("TESSDATA_PREFIX", &dir);
+
+    Ok(dir)
+}
+
+/// Brings the extracted copies of the bundled files up to date with the binary.
+/// Files are only hashed when the program version changed or their size or
+/// modification time differs from the manifest, so a normal start stays cheap.
+fn sync_bundled(dir: &Path) -> TessdataResult<()> {
+    fs::create_dir_all(dir)?;
+    let old = Manifest::load(dir);
+    let same_version = old.version == env!("CARGO_PKG_VERSION");
+    let mut manifest = Manifest {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        files: BTreeMap::new(),
+        linked: old.linked.clone(),
+    };
+
+    for file in bundled_files() {
+        let name = file_name(file);
+        let path = dir.join(&name);
+        let recorded = old.files.get(&name);
+
+        // The manifest holds the hash of the bundled file as long as the
+        // version is unchanged; after an upgrade it has to be computed again.
+        let sha256 = match recorded {
+            Some(entry) if same_version => entry.sha256.clone(),
+            _ => sha256_hex(file.contents()),
+        };
+        let up_to_date = match (recorded, Stamp::of(&path)) {
+            (Some(entry), Some(stamp)) if entry.sha256 == sha256 && entry.stamp == stamp => true,
+            (_, Some(_)) => sha256_file(&path)? == sha256,
+            (_, None) => false,
+        };
+        if !up_to_date {
+            write_atomically(&path, file.contents())?;
+        }
+
+        let stamp = Stamp::of(&path)
+            .ok_or_else(|| format!("Failed to read '{}' after writing it.", path.display()))?;
+        manifest.files.insert(name, ManifestEntry { sha256, stamp });
+    }
+
+    // Languages an older version bundled and this one does not are removed,
+    // unless the user replaced the file in the meantime.
+    for (name, entry) in &old.files {
+        let path = dir.join(name);
+        if !manifest.files.contains_key(name) && Stamp::of(&path) == Some(entry.stamp) {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    if manifest != old {
+        manifest.save(dir)?;
+    }
+    Ok(())
+}
+
+/// Links every `.traineddata` in `custom_dir` into `dir`, unless `dir` has a
+/// file of that name already: bundled and installed languages come first.
+/// The links of the previous start are removed first, so a language that is
+/// gone from `custom_dir`, or a `custom_dir` that changed, leaves none behind.
+fn link_custom(dir: &Path, custom_dir: Option<&Path>) -> TessdataResult<()> {
+    let mut manifest = Manifest::load(dir);
+    let old = std::mem::take(&mut manifest.linked);
+    for name in &old {
+        let path = dir.join(name);
+        // A file installed over the link since is the user's now.
+        if fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    if let Some(custom_dir) = custom_dir {
+        let custom_dir = fs::canonicalize(custom_dir)?;
+        for entry in fs::read_dir(&custom_dir)? {
+            let source = entry?.path();
+            if source.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            let Some(name) = source.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let path = dir.join(name);
+            if fs::symlink_metadata(&path).is_ok() {
+                continue;
+            }
+            std::os::unix::fs::symlink(&source, &path)?;
+            manifest.linked.insert(name.to_string());
+        }
+    }
+
+    if manifest.linked != old {
+        manifest.save(dir)?;
+    }
+    Ok(())
+}
+
+// --- Language Packs ---
+
+/// Lists the `.traineddata` files in `dir`, sorted by code.
+pub fn list(dir: &Path) -> io::Result<Vec<LanguagePack>> {
+    let manifest = Manifest::load(dir);
+    let mut packs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        let Some(code) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        packs.push(LanguagePack {
+            code: code.to_string(),
+            size: fs::metadata(&path)?.len(),
+            bundled: manifest.files.contains_key(&file_name_for(code)),
+            linked: manifest.linked.contains(&file_name_for(code)),
+        });
+    }
+    packs.sort_by(|a, b| a.code.cmp(&b.code));
+    Ok(packs)
+}
+
+/// The codes of the languages in `dir` that Tesseract can recognize text with.
+pub fn languages(dir: &Path) -> io::Result<Vec<String>> {
+    Ok(list(dir)?
+        .into_iter()
+        .map(|pack| pack.code)
+        .filter(|code| !NON_LANGUAGES.contains(&code.as_str()))
+        .collect())
+}
+
+/// Copies a `.traineddata` file into `dir` and returns its language code.
+/// Files that do not look like Tesseract data are refused.
+pub fn install(dir: &Path, file: &Path) -> TessdataResult<String> {
+    let code = file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|_| file.extension().and_then(|e| e.to_str()) == Some(EXTENSION))
+        .ok_or_else(|| format!("'{}' is not a .{} file.", file.display(), EXTENSION))?
+        .to_string();
+    validate_code(&code)?;
+    if Manifest::load(dir)
+        .files
+        .contains_key(&file_name_for(&code))
+    {
+        return Err(format!("'{}' is bundled and already installed.", code).into());
+    }
+
+    let contents =
+        fs::read(file).map_err(|e| format!("Failed to read '{}': {}", file.display(), e))?;
+    if !looks_like_traineddata(&contents) {
+        return Err(format!("'{}' is not a Tesseract data file.", file.display()).into());
+    }
+    write_atomically(&dir.join(file_name_for(&code)), &contents)?;
+    Ok(code)
+}
+
+/// Deletes an installed language. Bundled languages cannot be removed; they
+/// would only be extracted again on the next start.
+pub fn remove(dir: &Path, code: &str) -> TessdataResult<()> {
+    validate_code(code)?;
+    let name = file_name_for(code);
+    let manifest = Manifest::load(dir);
+    if manifest.files.contains_key(&name) {
+        return Err(format!("'{}' is bundled and cannot be removed.", code).into());
+    }
+    if manifest.linked.contains(&name) {
+        return Err(format!(
+            "'{}' is linked from the --tessdata-dir directory; remove it there.",
+            code
+        )
+        .into());
+    }
+    match fs::remove_file(dir.join(&name)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(format!("'{}' is not installed in {}.", code, dir.display()).into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// --- Manifest ---
+
+impl Manifest {
+    /// A missing or unreadable manifest is treated as empty, so every file is checked.
+    fn load(dir: &Path) -> Self {
+        fs::read_to_string(dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, dir: &Path) -> io::Result<()> {
+        write_atomically(&dir.join(MANIFEST_FILE), &serde_json::to_vec_pretty(self)?)
+    }
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified_ns: modified.as_nanos(),
+        })
+    }
+}
+
+// --- Helper Functions ---
+
+fn bundled_files() -> impl Iterator<Item = &'static include_dir::File<'static>> {
+    BUNDLED
+        .files()
+        .filter(|file| file.path().extension().and_then(|e| e.to_str()) == Some(EXTENSION))
+}
+
+fn file_name(file: &include_dir::File) -> String {
+    file.path()
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Language codes become file names, so anything but letters, digits and `_`
+/// (e.g. a path like `../x`) is refused.
+fn validate_code(code: &str) -> TessdataResult<()> {
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("'{}' is not a valid language code.", code).into());
+    }
+    Ok(())
+}
+
+fn file_name_for(code: &str) -> String {
+    format!("{}.{}", code, EXTENSION)
+}
+
+/// Writes to a temporary file first, so an interrupted write never leaves a
+/// truncated file under the real name.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let partial = path.with_extension("part");
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, path)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A traineddata file starts with the number of components (an `i32`),
+/// followed by that many 64-bit offsets.
+fn looks_like_traineddata(contents: &[u8]) -> bool {
+    let Some(header) = contents.get(..4) else {
+        return false;
+    };
+    let entries = i32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    (1..=64).contains(&entries) && contents.len() > 4 + 8 * entries as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn traineddata(entries: i32) -> Vec<u8> {
+        let mut contents = entries.to_le_bytes().to_vec();
+        contents.resize(4 + 8 * entries as usize + 16, 0);
+        contents
+    }
+
+    #[test]
+    fn sync_extracts_and_repairs_bundled_files() {
+        let dir = paths::test_dir("tessdata-sync");
+        sync_bundled(&dir).unwrap();
+        let manifest = Manifest::load(&dir);
+        assert_eq!(manifest.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest.files.len(), bundled_files().count());
+
+        let eng = dir.join("eng.traineddata");
+        fs::write(&eng, b"damaged").unwrap();
+        // An outdated version makes every file be hashed again.
+        let mut outdated = Manifest::load(&dir);
+        outdated.version = "0.0.0".to_string();
+        outdated.save(&dir).unwrap();
+        sync_bundled(&dir).unwrap();
+        assert_eq!(
+            sha256_file(&eng).unwrap(),
+            manifest.files["eng.traineddata"].sha256
+        );
+        assert_eq!(Manifest::load(&dir).version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn sync_removes_files_no_longer_bundled() {
+        let dir = paths::test_dir("tessdata-dropped");
+        sync_bundled(&dir).unwrap();
+        let old = dir.join("old.traineddata");
+        fs::write(&old, traineddata(1)).unwrap();
+        let mut manifest = Manifest::load(&dir);
+        manifest.files.insert(
+            "old.traineddata".to_string(),
+            ManifestEntry {
+                sha256: sha256_file(&old).unwrap(),
+                stamp: Stamp::of(&old).unwrap(),
+            },
+        );
+        manifest.save(&dir).unwrap();
+
+        sync_bundled(&dir).unwrap();
+        assert!(!old.exists());
+        assert!(!Manifest::load(&dir).files.contains_key("old.traineddata"));
+    }
+
+    #[test]
+    fn custom_languages_are_linked_next_to_the_bundled_ones() {
+        let dir = paths::test_dir("tessdata-linked");
+        let custom = paths::test_dir("tessdata-custom");
+        sync_bundled(&dir).unwrap();
+        fs::write(custom.join("vie.traineddata"), traineddata(1)).unwrap();
+        fs::write(custom.join("eng.traineddata"), traineddata(1)).unwrap();
+        fs::write(custom.join("notes.txt"), "").unwrap();
+
+        link_custom(&dir, Some(&custom)).unwrap();
+        let packs = list(&dir).unwrap();
+        let pack = |code: &str| packs.iter().find(|pack| pack.code == code).unwrap();
+        assert!(pack("vie").linked && !pack("vie").bundled);
+        assert!(pack("eng").bundled && !pack("eng").linked);
+        assert!(remove(&dir, "vie").is_err());
+        // The bundled eng was not replaced by the custom one.
+        assert!(fs::metadata(dir.join("eng.traineddata")).unwrap().len() > 1000);
+
+        link_custom(&dir, None).unwrap();
+        assert!(fs::symlink_metadata(dir.join("vie.traineddata")).is_err());
+        assert!(Manifest::load(&dir).linked.is_empty());
+    }
+
+    #[test]
+    fn install_refuses_bad_codes_and_files() {
+        let dir = paths::test_dir("tessdata-install");
+        let source = paths::test_dir("tessdata-source");
+        let good = source.join("vie.traineddata");
+        fs::write(&good, traineddata(3)).unwrap();
+        let bad = source.join("fake.traineddata");
+        fs::write(&bad, b"<html>not found</html>").unwrap();
+
+        assert_eq!(install(&dir, &good).unwrap(), "vie");
+        assert!(install(&dir, &bad).is_err());
+        assert!(install(&dir, &source.join("vie.txt")).is_err());
+        remove(&dir, "vie").unwrap();
+        assert!(remove(&dir, "vie").is_err());
+    }
+
+    #[test]
+    fn codes_must_be_plain_names() {
+        for code in ["eng", "chi_sim", "jpn_vert", "script2"] {
+            assert!(validate_code(code).is_ok(), "{}", code);
+        }
+        for code in ["", "../eng", "a/b", "eng.traineddata", "e ng"] {
+            assert!(validate_code(code).is_err(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn traineddata_header_is_checked() {
+        assert!(looks_like_traineddata(&traineddata(1)));
+        assert!(looks_like_traineddata(&traineddata(64)));
+        assert!(!looks_like_traineddata(&traineddata(65)));
+        assert!(!looks_like_traineddata(&0i32.to_le_bytes()));
+        assert!(!looks_like_traineddata(b"abc"));
+        // A header that promises more entries than the file holds.
+        assert!(!looks_like_traineddata(&traineddata(8)[..20]));
+    }
+}
@@ -1,6 +1,67 @@
+use crate::paths;
+use crate::stardict::StarDictProvider;
+use clap::ValueEnum;
+use futures_util::future::BoxFuture;
 use regex::Regex;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub type TranslateResult<T> = Result<T, TranslationError>;
+
+// --- Errors ---
+
+/// Why a translation failed, in terms the UI can explain to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranslationError {
+    /// The service could not be reached or answered with an HTTP error.
+    Network(String),
+    /// The service did not answer within `--timeout-secs`.
+    Timeout,
+    /// The response did not have the expected format.
+    Parse(String),
+    /// The service refused the request because too many were sent (HTTP 429).
+    RateLimited,
+    /// Every provider succeeded, but none of them knew the text.
+    NoResults,
+}
+
+impl fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Network(message) => write!(f, "Network error: {}", message),
+            Self::Timeout => write!(f, "The translation service did not respond in time"),
+            Self::Parse(message) => write!(f, "Unexpected response: {}", message),
+            Self::RateLimited => write!(f, "Too many requests; the service is rate limiting"),
+            Self::NoResults => write!(f, "No translation found"),
+        }
+    }
+}
+
+impl std::error::Error for TranslationError {}
+
+impl From<reqwest::Error> for TranslationError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout
+        } else if e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) {
+            Self::RateLimited
+        } else if e.is_decode() {
+            Self::Parse(e.to_string())
+        } else {
+            Self::Network(e.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for TranslationError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Parse(e.to_string())
+    }
+}
 
 // --- Data Structures ---
 
@@ -24,6 +85,15 @@ pub struct LongdoData {
     pub examples: Vec<ExampleItem>,
 }
 
+/// The result of one provider other than Google and Longdo, shown as its own section.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderSection {
+    pub provider: String,
+    pub translation: Option<String>,
+    #[serde(default)]
+    pub entries: Vec<TranslationItem>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CombinedTranslationData {
     pub search_word: String,
@@ -31,6 +101,69 @@ pub struct CombinedTranslationData {
     pub target_lang: String,
     pub google_translation: String,
     pub longdo_data: Option<LongdoData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sections: Vec<ProviderSection>,
+    /// Providers that failed while others returned results. A result with
+    /// errors is incomplete, so it is not cached.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ProviderError>,
+}
+
+/// A provider that failed during an otherwise successful lookup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderError {
+    pub provider: String,
+    pub message: String,
+}
+
+impl CombinedTranslationData {
+    /// A result with only a Google translation, for tests.
+    #[cfg(test)]
+    pub fn sample(word: &str, translation: &str) -> Self {
+        Self {
+            search_word: word.to_string(),
+            source_lang: "EN".to_string(),
+            target_lang: "TH".to_string(),
+            google_translation: translation.to_string(),
+            longdo_data: None,
+            sections: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn has_results(&self) -> bool {
+        !self.google_translation.is_empty()
+            || self.longdo_data.is_some()
+            || !self.sections.is_empty()
+    }
+
+    /// The detected source language, or `None` while no provider has detected one yet.
+    pub fn detected_source(&self) -> Option<&str> {
+        if self.source_lang.is_empty() || self.source_lang.eq_ignore_ascii_case("auto") {
+            None
+        } else {
+            Some(&self.source_lang)
+        }
+    }
+
+    /// A one-line translation for lists: Google's, else the first other provider's.
+    pub fn summary(&self) -> &str {
+        if !self.google_translation.is_empty() {
+            return &self.google_translation;
+        }
+        let from_sections = self.sections.iter().find_map(|section| {
+            section.translation.as_deref().or(section
+                .entries
+                .first()
+                .map(|item| item.translation.as_str()))
+        });
+        let from_longdo = self
+            .longdo_data
+            .as_ref()
+            .and_then(|longdo| longdo.translations.first())
+            .map(|item| item.translation.as_str());
+        from_sections.or(from_longdo).unwrap_or_default()
+    }
 }
 
 // --- Helper Functions ---
@@ -40,45 +173,308 @@ pub fn is_single_word(text: &str) -> bool {
     !trimmed.contains(char::is_whitespace) && trimmed.len() < 50
 }
 
-// --- Core Translation Logic ---
+/// Builds the HTTP client shared by all network providers.
+pub fn http_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .unwrap_or_default()
+}
 
-pub async fn translate_text(
-    text: &str,
-    source: &str, // Expects "auto" from main.rs
-    target: &str,
-) -> Result<CombinedTranslationData, Box<dyn std::error::Error + Send + Sync>> {
-    let search_word = text.trim().to_string();
+// --- Providers ---
+
+/// What a provider is asked to translate.
+#[derive(Debug, Clone, Copy)]
+pub struct TranslationQuery<'a> {
+    pub text: &'a str,
+    pub source: &'a str,
+    pub target: &'a str,
+}
+
+/// A translation or dictionary service.
+///
+/// Providers run one after another and add their results to the shared
+/// `CombinedTranslationData`, so a provider can rely on the source language
+/// detected by the ones before it.
+pub trait TranslationProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn translate<'a>(
+        &'a self,
+        query: TranslationQuery<'a>,
+        data: &'a mut CombinedTranslationData,
+    ) -> BoxFuture<'a, TranslateResult<()>>;
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// Google Translate; also detects the source language.
+    Google,
+    /// Longdo dictionary definitions and examples (single English words, to Thai).
+    Longdo,
+    /// A LibreTranslate-compatible server (see `--libretranslate-url`).
+    Libretranslate,
+    /// Local StarDict dictionaries (see `--stardict-dir`); works offline.
+    Stardict,
+}
+
+/// The Longdo dictionaries shown by default, in this order.
+pub const DEFAULT_LONGDO_DICTIONARIES: &[&str] = &[
+    "NECTEC Lexitron Dictionary EN-TH",
+    "Nontri Dictionary",
+    "Hope Dictionary",
+];
+
+/// Settings needed to construct the providers.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderOptions {
+    pub timeout: Duration,
+    pub libretranslate_url: Option<String>,
+    pub libretranslate_api_key: Option<String>,
+    /// Directory searched for StarDict dictionaries; defaults to the data directory.
+    pub stardict_dir: Option<PathBuf>,
+    /// Longdo dictionaries whose definitions are shown, in this order.
+    pub longdo_dictionaries: Vec<String>,
+}
 
-    // Step 1: Translate with Google to get both the translation and the detected source language.
-    let (google_translation, detected_source_lang) =
-        google_translate_with_source_detection(&search_word, target, source).await?;
+/// Runs a fixed list of providers and merges their results.
+/// Cheap to clone, so it can be handed to background threads.
+#[derive(Clone)]
+pub struct Translator {
+    providers: Arc<Vec<Box<dyn TranslationProvider>>>,
+}
 
-    let mut longdo_data: Option<LongdoData> = None;
+impl Translator {
+    pub fn new(
+        kinds: &[ProviderKind],
+        options: &ProviderOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = http_client(options.timeout);
+        let mut providers: Vec<Box<dyn TranslationProvider>> = Vec::new();
+
+        for kind in kinds {
+            let provider: Box<dyn TranslationProvider> = match kind {
+                ProviderKind::Google => Box::new(GoogleProvider {
+                    client: client.clone(),
+                }),
+                ProviderKind::Longdo => Box::new(LongdoProvider {
+                    client: client.clone(),
+                    dictionaries: options.longdo_dictionaries.clone(),
+                }),
+                ProviderKind::Libretranslate => {
+                    let url = options.libretranslate_url.clone().ok_or(
+                        "The libretranslate provider needs --libretranslate-url to be set.",
+                    )?;
+                    Box::new(LibreTranslateProvider::new(
+                        client.clone(),
+                        url,
+                        options.libretranslate_api_key.clone(),
+                    ))
+                }
+                ProviderKind::Stardict => {
+                    let dir = match &options.stardict_dir {
+                        Some(dir) => dir.clone(),
+                        None => paths::app_data_dir()?.join("stardict"),
+                    };
+                    Box::new(StarDictProvider::new(&dir))
+                }
+            };
+            providers.push(provider);
+        }
 
-    // Step 2: If the detected language is English, target is Thai, and it's a single word, fetch Longdo data.
-    if is_single_word(&search_word) && detected_source_lang == "en" && target == "th" {
-        // Since the conditions are met, we can now fetch from Longdo.
-        // We call this sequentially because the decision to call it depends on the result from Google.
-        longdo_data = fetch_longdo_translation(&search_word).await.ok();
+        if providers.is_empty() {
+            return Err("At least one translation provider is required.".into());
+        }
+
+        Ok(Self {
+            providers: Arc::new(providers),
+        })
     }
 
-    // Step 3: Combine all data and return.
-    Ok(CombinedTranslationData {
-        search_word,
-        source_lang: detected_source_lang.to_uppercase(), // Use the language Google detected
-        target_lang: target.to_uppercase(),
-        google_translation,
-        longdo_data,
-    })
+    /// A short description of the provider list, e.g. `google+longdo`.
+    /// Results from different provider lists are cached separately.
+    pub fn id(&self) -> String {
+        self.providers
+            .iter()
+            .map(|p| p.name())
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
+    /// Translates text with every provider and merges the results.
+    /// A failing provider is skipped as long as another one produced a result.
+    pub async fn translate(
+        &self,
+        text: &str,
+        source: &str, // Expects "auto" from main.rs
+        target: &str,
+    ) -> TranslateResult<CombinedTranslationData> {
+        let search_word = text.trim().to_string();
+        let mut data = CombinedTranslationData {
+            search_word: search_word.clone(),
+            source_lang: String::new(),
+            target_lang: target.to_uppercase(),
+            google_translation: String::new(),
+            longdo_data: None,
+            sections: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        let query = TranslationQuery {
+            text: &search_word,
+            source,
+            target,
+        };
+        let mut first_error = None;
+        for provider in self.providers.iter() {
+            if let Err(e) = provider.translate(query, &mut data).await {
+                data.errors.push(ProviderError {
+                    provider: provider.name().to_string(),
+                    message: e.to_string(),
+                });
+                first_error.get_or_insert(e);
+            }
+        }
+
+        if !data.has_results() {
+            return Err(first_error.unwrap_or(TranslationError::NoResults));
+        }
+        if data.source_lang.is_empty() {
+            data.source_lang = source.to_uppercase();
+        }
+
+        Ok(data)
+    }
+}
+
+/// Google Translate. Fills `google_translation` and the detected source language.
+struct GoogleProvider {
+    client: reqwest::Client,
+}
+
+impl TranslationProvider for GoogleProvider {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        query: TranslationQuery<'a>,
+        data: &'a mut CombinedTranslationData,
+    ) -> BoxFuture<'a, TranslateResult<()>> {
+        Box::pin(async move {
+            let (google_translation, detected_source_lang) =
+                google_translate_with_source_detection(
+                    &self.client,
+                    query.text,
+                    query.target,
+                    query.source,
+                )
+                .await?;
+
+            data.google_translation = google_translation;
+            data.source_lang = detected_source_lang.to_uppercase(); // Use the language Google detected
+            Ok(())
+        })
+    }
+}
+
+/// Longdo Dict. Only consulted for single English words translated to Thai.
+struct LongdoProvider {
+    client: reqwest::Client,
+    dictionaries: Vec<String>,
+}
+
+impl TranslationProvider for LongdoProvider {
+    fn name(&self) -> &'static str {
+        "longdo"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        query: TranslationQuery<'a>,
+        data: &'a mut CombinedTranslationData,
+    ) -> BoxFuture<'a, TranslateResult<()>> {
+        Box::pin(async move {
+            // Without a detected language (e.g. Google is disabled), fall back to
+            // treating plain ASCII words as English.
+            let is_english = match data.detected_source() {
+                Some(lang) => lang.eq_ignore_ascii_case("en"),
+                None => query.text.chars().all(|c| c.is_ascii_alphabetic()),
+            };
+
+            if is_single_word(query.text) && is_english && query.target == "th" {
+                data.longdo_data = Some(
+                    fetch_longdo_translation(&self.client, query.text, &self.dictionaries).await?,
+                );
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A LibreTranslate-compatible HTTP API, e.g. a self-hosted instance.
+struct LibreTranslateProvider {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+impl LibreTranslateProvider {
+    fn new(client: reqwest::Client, url: String, api_key: Option<String>) -> Self {
+        Self {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+}
+
+impl TranslationProvider for LibreTranslateProvider {
+    fn name(&self) -> &'static str {
+        "libretranslate"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        query: TranslationQuery<'a>,
+        data: &'a mut CombinedTranslationData,
+    ) -> BoxFuture<'a, TranslateResult<()>> {
+        Box::pin(async move {
+            let (translation, detected_source_lang) = libretranslate(
+                &self.client,
+                &self.url,
+                self.api_key.as_deref(),
+                query.text,
+                query.target,
+                query.source,
+            )
+            .await?;
+
+            if data.detected_source().is_none() {
+                if let Some(lang) = detected_source_lang {
+                    data.source_lang = lang.to_uppercase();
+                }
+            }
+            data.sections.push(ProviderSection {
+                provider: "LibreTranslate".to_string(),
+                translation: Some(translation),
+                entries: Vec::new(),
+            });
+            Ok(())
+        })
+    }
 }
 
 // --- Service-Specific Fetchers ---
 
 async fn google_translate_with_source_detection(
+    client: &reqwest::Client,
     text: &str,
     target_lang: &str,
     source_lang: &str,
-) -> Result<(String, String), Box<dyn std::error::Error + Send + Sync>> {
+) -> TranslateResult<(String, String)> {
     let url = format!(
         "https://translate.googleapis.com/translate_a/single?client=gtx&sl={}&tl={}&dt=t&q={}",
         source_lang,
@@ -86,8 +482,7 @@ async fn google_translate_with_source_detection(
         urlencoding::encode(text)
     );
 
-    let client = reqwest::Client::new();
-    let response = client.get(&url).send().await?;
+    let response = client.get(&url).send().await?.error_for_status()?;
     let json: serde_json::Value = response.json().await?;
 
     // Extract the translated text segments.
@@ -100,7 +495,9 @@ async fn google_translate_with_source_detection(
         }
         result
     } else {
-        return Err("Failed to parse Google Translate translation".into());
+        return Err(TranslationError::Parse(
+            "Failed to parse Google Translate translation".to_string(),
+        ));
     };
 
     // Extract the detected source language.
@@ -108,45 +505,104 @@ async fn google_translate_with_source_detection(
         .get(2)
         .and_then(|v| v.as_str())
         .map(|s| s.to_string())
-        .ok_or("Failed to parse detected source language from Google")?;
+        .ok_or_else(|| {
+            TranslationError::Parse(
+                "Failed to parse detected source language from Google".to_string(),
+            )
+        })?;
+
+    Ok((translation, detected_lang))
+}
+
+async fn libretranslate(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: Option<&str>,
+    text: &str,
+    target_lang: &str,
+    source_lang: &str,
+) -> TranslateResult<(String, Option<String>)> {
+    // LibreTranslate uses bare ISO 639-1 codes ("zh", not "zh-CN").
+    let bare_code = |lang: &str| lang.split('-').next().unwrap_or(lang).to_lowercase();
+
+    let mut body = serde_json::json!({
+        "q": text,
+        "source": bare_code(source_lang),
+        "target": bare_code(target_lang),
+        "format": "text",
+    });
+    if let Some(key) = api_key {
+        body["api_key"] = key.into();
+    }
+
+    let response = client
+        .post(format!("{}/translate", base_url))
+        .json(&body)
+        .send()
+        .await?;
+    let status = response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(TranslationError::RateLimited);
+    }
+    let json: serde_json::Value = response.json().await?;
+
+    if !status.is_success() {
+        let message = json
+            .get("error")
+            .and_then(|v| v.as_str())
+            .unwrap_or("request failed");
+        return Err(TranslationError::Network(format!(
+            "LibreTranslate returned {}: {}",
+            status, message
+        )));
+    }
+
+    let translation = json
+        .get("translatedText")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| {
+            TranslationError::Parse("Failed to parse LibreTranslate translation".to_string())
+        })?
+        .to_string();
+    let detected_lang = json
+        .get("detectedLanguage")
+        .and_then(|v| v.get("language"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
 
     Ok((translation, detected_lang))
 }
 
 async fn fetch_longdo_translation(
+    client: &reqwest::Client,
     word: &str,
-) -> Result<LongdoData, Box<dyn std::error::Error + Send + Sync>> {
+    dictionaries: &[String],
+) -> TranslateResult<LongdoData> {
     let url = format!("https://dict.longdo.com/mobile.php?search={}", word);
-    let client = reqwest::Client::new();
     let response = client
         .get(&url)
         .header(
             "User-Agent",
             "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
         )
-        .timeout(std::time::Duration::from_secs(10))
         .send()
-        .await?;
+        .await?
+        .error_for_status()?;
 
     let html = response.text().await?;
-    parse_longdo_html(&html)
+    parse_longdo_html(&html, dictionaries)
 }
 
 // --- HTML Parsing Logic for Longdo (Adapted from user's working code) ---
 
-fn parse_longdo_html(html: &str) -> Result<LongdoData, Box<dyn std::error::Error + Send + Sync>> {
+fn parse_longdo_html(html: &str, target_dicts: &[String]) -> TranslateResult<LongdoData> {
     let document = Html::parse_document(html);
     let mut data = LongdoData::default();
 
-    let target_dicts = vec![
-        "NECTEC Lexitron Dictionary EN-TH",
-        "Nontri Dictionary",
-        "Hope Dictionary",
-    ];
     let b_selector = Selector::parse("b").unwrap();
 
     // Parse translations by finding the dictionary header first.
-    for dict_name in &target_dicts {
+    for dict_name in target_dicts {
         for b_element in document.select(&b_selector) {
             let text = b_element.text().collect::<String>();
             if text.contains(dict_name) {
@@ -196,7 +652,8 @@ fn parse_translation_table(table: &scraper::ElementRef, data: &mut LongdoData, d
     }
 }
 
-fn parse_definition(definition: &str) -> (String, String) {
+/// Splits a dictionary definition like `(n) คำแปล` into its part of speech and text.
+pub fn parse_definition(definition: &str) -> (String, String) {
     let re = Regex::new(r"^\s*\((.*?)\)\s*(.*)").unwrap();
 
     if let Some(caps) = re.captures(definition) {
@@ -0,0 +1,332 @@
+use crate::paths;
+use crate::translation::{CombinedTranslationData, ExampleItem, TranslationItem};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// How long `save` waits for another process to finish writing `vocab.json`.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+/// A lock file older than this was left behind by a process that crashed.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(30);
+
+/// SM-2 starts every card at this ease factor and never lets it drop below the minimum.
+const INITIAL_EASE: f32 = 2.5;
+const MIN_EASE: f32 = 1.3;
+
+// --- Data Structures ---
+
+/// A saved word with its definitions and its spaced-repetition schedule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VocabCard {
+    pub word: String,
+    /// A one-line translation, see `CombinedTranslationData::summary`.
+    pub translation: String,
+    pub source_lang: String,
+    pub target_lang: String,
+    pub definitions: Vec<TranslationItem>,
+    pub examples: Vec<ExampleItem>,
+    pub added_at: u64, // Seconds since the Unix epoch
+    // SM-2 state
+    pub repetitions: u32,
+    pub interval_days: u32,
+    pub ease: f32,
+    pub due_at: u64,
+}
+
+/// How well a word was remembered during review.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+/// The saved words, stored as `vocab.json` in the data directory.
+/// Several windows (and `review`) may have it open at once, so `save` only
+/// writes the cards this one changed on top of what is on disk.
+#[derive(Debug)]
+pub struct Vocabulary {
+    path: PathBuf,
+    pub cards: Vec<VocabCard>,
+    /// Lowercased words of the cards changed since the last save.
+    changed: HashSet<String>,
+}
+
+/// Held while `vocab.json` is read and rewritten; removed when dropped.
+struct LockFile(PathBuf);
+
+// --- Vocabulary Implementation ---
+
+impl Vocabulary {
+    /// Loads the saved words. A missing file gives an empty list.
+    pub fn open() -> io::Result<Self> {
+        let dir = paths::app_data_dir()?;
+        fs::create_dir_all(&dir)?;
+        Self::at(dir.join("vocab.json"))
+    }
+
+    fn at(path: PathBuf) -> io::Result<Self> {
+        let cards = read_cards(&path)?;
+        Ok(Self {
+            path,
+            cards,
+            changed: HashSet::new(),
+        })
+    }
+
+    /// Writes the changed cards. The file is read again under a lock and
+    /// merged, so words saved or reviewed by another process since `open`
+    /// are kept.
+    pub fn save(&mut self) -> io::Result<()> {
+        if self.changed.is_empty() {
+            return Ok(());
+        }
+        let _lock = LockFile::acquire(self.path.with_extension("json.lock"))?;
+
+        let mut cards = read_cards(&self.path)?;
+        for card in &self.cards {
+            if !self.changed.contains(&card.word.to_lowercase()) {
+                continue;
+            }
+            match cards
+                .iter_mut()
+                .find(|saved| saved.word.eq_ignore_ascii_case(&card.word))
+            {
+                Some(saved) => *saved = card.clone(),
+                None => cards.push(card.clone()),
+            }
+        }
+
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&cards)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+        self.changed.clear();
+        Ok(())
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.find(word).is_some()
+    }
+
+    /// Saves a looked-up word with its Longdo (and other dictionary) definitions and examples.
+    /// Saving a word again refreshes its definitions but keeps its review schedule.
+    pub fn add(&mut self, word: &str, data: &CombinedTranslationData) {
+        let mut definitions: Vec<TranslationItem> = data
+            .longdo_data
+            .iter()
+            .flat_map(|longdo| longdo.translations.iter().cloned())
+            .collect();
+        definitions.extend(
+            data.sections
+                .iter()
+                .flat_map(|section| section.entries.iter().cloned()),
+        );
+        let examples = data
+            .longdo_data
+            .iter()
+            .flat_map(|longdo| longdo.examples.iter().cloned())
+            .collect();
+
+        let word = word.trim();
+        self.changed.insert(word.to_lowercase());
+        if let Some(index) = self.find(word) {
+            let card = &mut self.cards[index];
+            card.translation = data.summary().to_string();
+            card.definitions = definitions;
+            card.examples = examples;
+            return;
+        }
+
+        let now = now_secs();
+        self.cards.push(VocabCard {
+            word: word.to_string(),
+            translation: data.summary().to_string(),
+            source_lang: data.source_lang.clone(),
+            target_lang: data.target_lang.clone(),
+            definitions,
+            examples,
+            added_at: now,
+            repetitions: 0,
+            interval_days: 0,
+            ease: INITIAL_EASE,
+            due_at: now,
+        });
+    }
+
+    /// Reschedules a card after review; see `VocabCard::review`.
+    pub fn review(&mut self, index: usize, grade: Grade) {
+        let card = &mut self.cards[index];
+        card.review(grade);
+        self.changed.insert(card.word.to_lowercase());
+    }
+
+    /// Indices of the cards due for review, the most overdue first.
+    pub fn due_cards(&self) -> Vec<usize> {
+        let now = now_secs();
+        let mut due: Vec<usize> = (0..self.cards.len())
+            .filter(|&i| self.cards[i].due_at <= now)
+            .collect();
+        due.sort_by_key(|&i| self.cards[i].due_at);
+        due
+    }
+
+    fn find(&self, word: &str) -> Option<usize> {
+        let word = word.trim();
+        self.cards
+            .iter()
+            .position(|card| card.word.eq_ignore_ascii_case(word))
+    }
+}
+
+impl VocabCard {
+    /// Reschedules the card with the SM-2 algorithm.
+    pub fn review(&mut self, grade: Grade) {
+        // SM-2 rates answers from 0 to 5; below 3 counts as forgotten.
+        let quality: u32 = match grade {
+            Grade::Again => 1,
+            Grade::Hard => 3,
+            Grade::Good => 4,
+            Grade::Easy => 5,
+        };
+
+        if quality < 3 {
+            self.repetitions = 0;
+            self.interval_days = 1;
+        } else {
+            self.interval_days = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval_days as f32 * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        }
+
+        let miss = (5 - quality) as f32;
+        self.ease = (self.ease + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE);
+        self.due_at = now_secs() + self.interval_days as u64 * SECONDS_PER_DAY;
+    }
+}
+
+impl LockFile {
+    fn acquire(path: PathBuf) -> io::Result<Self> {
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self(path)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age > STALE_LOCK_AGE);
+                    if stale {
+                        let _ = fs::remove_file(&path);
+                    } else if started.elapsed() > LOCK_TIMEOUT {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            format!("{} is held by another process", path.display()),
+                        ));
+                    } else {
+                        thread::sleep(Duration::from_millis(20));
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+// --- Helper Functions ---
+
+/// Reads `vocab.json`. A missing file gives an empty list.
+fn read_cards(path: &Path) -> io::Result<Vec<VocabCard>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_from_two_windows_are_merged() {
+        let path = paths::test_dir("vocab-merge").join("vocab.json");
+        let mut first = Vocabulary::at(path.clone()).unwrap();
+        let mut second = Vocabulary::at(path.clone()).unwrap();
+
+        first.add("hello", &CombinedTranslationData::sample("hello", "สวัสดี"));
+        first.save().unwrap();
+        second.add("world", &CombinedTranslationData::sample("world", "โลก"));
+        second.save().unwrap();
+
+        let words: Vec<String> = Vocabulary::at(path.clone())
+            .unwrap()
+            .cards
+            .into_iter()
+            .map(|card| card.word)
+            .collect();
+        assert_eq!(words, ["hello", "world"]);
+        assert!(!path.with_extension("json.lock").exists());
+    }
+
+    #[test]
+    fn review_keeps_words_saved_elsewhere() {
+        let path = paths::test_dir("vocab-review").join("vocab.json");
+        let mut window = Vocabulary::at(path.clone()).unwrap();
+        window.add("hello", &CombinedTranslationData::sample("hello", "สวัสดี"));
+        window.save().unwrap();
+
+        let mut review = Vocabulary::at(path.clone()).unwrap();
+        window.add("world", &CombinedTranslationData::sample("world", "โลก"));
+        window.save().unwrap();
+        review.review(0, Grade::Good);
+        review.save().unwrap();
+
+        let cards = Vocabulary::at(path).unwrap().cards;
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].repetitions, 1);
+        assert_eq!(cards[1].word, "world");
+    }
+
+    #[test]
+    fn stale_lock_is_taken_over() {
+        let path = paths::test_dir("vocab-lock").join("vocab.json");
+        let lock_path = path.with_extension("json.lock");
+        fs::write(&lock_path, "").unwrap();
+        let old = SystemTime::now() - 2 * STALE_LOCK_AGE;
+        fs::File::options()
+            .write(true)
+            .open(&lock_path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let mut vocabulary = Vocabulary::at(path).unwrap();
+        vocabulary.add("hello", &CombinedTranslationData::sample("hello", "สวัสดี"));
+        vocabulary.save().unwrap();
+        assert!(!lock_path.exists());
+    }
+}
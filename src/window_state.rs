@@ -0,0 +1,36 @@
+use crate::paths;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Window settings that survive restarts, stored as `window.json` in the data directory.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct WindowState {
+    /// Outer position of the window the last time it was pinned: where it was
+    /// closed, unpinned or last moved to.
+    pub pinned_position: Option<[f32; 2]>,
+}
+
+impl WindowState {
+    /// Loads the saved state. A missing or unreadable file gives the defaults.
+    pub fn load() -> Self {
+        state_path()
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = state_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+fn state_path() -> io::Result<PathBuf> {
+    Ok(paths::app_data_dir()?.join("window.json"))
+}